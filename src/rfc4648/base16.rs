@@ -2,14 +2,152 @@
 
 use std::vec;
 
-enum DecodeSize {
-    Done(uint),       // on success
-    Fail(uint, ~str), // on failure
+use codec::{Codec, FromDecoded};
+use display::Display;
+use util::{DecodeSize, Done, Next, Fail, input_at};
+
+/// The RFC 4648 base16 encoding, in the same shape as `Base32Type` and
+/// `Base64Type` so that it can be used wherever a `Codec` is expected.
+#[deriving(Eq, Clone)]
+pub enum Base16Type {
+    Standard,
+}
+
+impl Base16Type {
+    /// Encodes `src` into a newly allocated vector.
+    pub fn encode(self, src: &[u8]) -> ~[u8] {
+        encode(src)
+    }
+    /// Decodes `src` into a newly allocated vector, failing on bad input.
+    pub fn decode(self, src: &[u8]) -> ~[u8] {
+        decode(src)
+    }
+    /// Decodes `src` into a newly allocated vector.
+    pub fn decode_result(self, src: &[u8]) -> Result<~[u8], ~str> {
+        decode_result(src)
+    }
+    /// Decodes `src` into a newly allocated vector. On failure, returns the
+    /// bytes decoded up to the last complete valid quantum along with the
+    /// reason.
+    pub fn decode_partial(self, src: &[u8]) -> Result<~[u8], (~[u8], ~str)> {
+        decode_partial(src)
+    }
+    /// Encodes the first `n` bytes of `buf` over itself and returns the
+    /// encoded length. `buf` must be at least `2 * n` bytes long.
+    pub fn encode_in_place(self, buf: &mut [u8], n: uint) -> uint {
+        encode_in_place(buf, n)
+    }
+    /// Decodes the encoded text in `buf` over itself and returns the decoded
+    /// length. On failure, returns the number of bytes decoded before the
+    /// error along with the reason, and `buf` starts with those bytes.
+    pub fn decode_in_place(self, buf: &mut [u8]) -> Result<uint, (uint, ~str)> {
+        decode_in_place(buf)
+    }
+    /// Checks `src` without producing any output and returns its decoded
+    /// length.
+    pub fn validate(self, src: &[u8]) -> Result<uint, ~str> {
+        validate(src)
+    }
+    /// Returns true if `src` is well-formed.
+    pub fn is_valid(self, src: &[u8]) -> bool {
+        is_valid(src)
+    }
+    /// Encodes `src` into a newly allocated string.
+    pub fn encode_to_string(self, src: &[u8]) -> ~str {
+        encode_to_string(src)
+    }
+    /// Decodes the string `src`, failing on bad input.
+    pub fn decode_str(self, src: &str) -> ~[u8] {
+        decode_str(src)
+    }
+    /// Decodes the string `src` into a newly allocated vector.
+    pub fn decode_str_result(self, src: &str) -> Result<~[u8], ~str> {
+        decode_str_result(src)
+    }
+    /// Formats `src` as hex without allocating, e.g.
+    /// `format!("{}", base16::Standard.display(digest))`.
+    pub fn display<'a>(self, src: &'a [u8]) -> Display<'a, Base16Type> {
+        Display::new(self, src)
+    }
+}
+
+impl Codec for Base16Type {
+    fn encoded_len(&self, n: uint) -> uint {
+        n * 2
+    }
+    fn block_len(&self) -> (uint, uint) {
+        (1, 2)
+    }
+    fn decoded_len(&self, n: uint) -> uint {
+        n / 2
+    }
+    fn encode_to(&self, dst: &mut [u8], src: &[u8]) {
+        base16_encode(BASE16_TABLE, dst, src)
+    }
+    fn decode_partial_to(&self, dst: &mut [u8], src: &[u8]) -> Result<uint, (uint, ~str)> {
+        decode_partial_to(dst, src)
+    }
+    fn validate(&self, src: &[u8]) -> Result<uint, ~str> {
+        match base16_decode(BASE16_DECODE_MAP, &mut [], Some(src), true) {
+            Done(n) | Next(n) => Ok(n),
+            Fail(_, reason) => Err(reason)
+        }
+    }
+    fn decode_in_place(&self, buf: &mut [u8]) -> Result<uint, (uint, ~str)> {
+        match base16_decode(BASE16_DECODE_MAP, buf, None, false) {
+            Done(n) | Next(n) => Ok(n),
+            Fail(n, reason) => Err((n, reason))
+        }
+    }
+}
+
+/// Conversion of raw bytes to base16 text, e.g. `data.to_base16()`.
+pub trait ToBase16 {
+    fn to_base16(&self) -> ~str;
 }
 
-static BASE16_TABLE: &'static [u8] = bytes!("0123456789ABCDEF");
+/// Conversion of base16 text to raw bytes, e.g. `text.from_base16()`.
+pub trait FromBase16 {
+    fn from_base16(&self) -> Result<~[u8], ~str>;
 
-static BASE16_DECODE_MAP: [u8, ..256] = [
+    /// Decodes straight into a type such as a key or token.
+    fn from_base16_into<T: FromDecoded>(&self) -> Result<T, ~str> {
+        match self.from_base16() {
+            Ok(bytes) => FromDecoded::from_decoded(bytes),
+            Err(reason) => Err(reason)
+        }
+    }
+}
+
+impl<'a> ToBase16 for &'a [u8] {
+    fn to_base16(&self) -> ~str {
+        encode_to_string(*self)
+    }
+}
+
+impl ToBase16 for ~[u8] {
+    fn to_base16(&self) -> ~str {
+        encode_to_string(self.as_slice())
+    }
+}
+
+impl<'a> FromBase16 for &'a str {
+    fn from_base16(&self) -> Result<~[u8], ~str> {
+        decode_result(self.as_bytes())
+    }
+}
+
+impl FromBase16 for ~str {
+    fn from_base16(&self) -> Result<~[u8], ~str> {
+        decode_result(self.as_bytes())
+    }
+}
+
+/// The uppercase hex digits, for other encodings that escape bytes in hex.
+pub static BASE16_TABLE: &'static [u8] = bytes!("0123456789ABCDEF");
+
+/// Maps hex digits of either case to their values, and anything else to 255.
+pub static BASE16_DECODE_MAP: [u8, ..256] = [
     255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
     255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
     255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
@@ -37,6 +175,12 @@ pub fn encode(src: &[u8]) -> ~[u8] {
     dst
 }
 
+/// Encodes `src` into a newly allocated string.
+pub fn encode_to_string(src: &[u8]) -> ~str {
+    let codec = &Standard as &Codec;
+    codec.encode_to_string(src)
+}
+
 // TODO: doc
 pub fn decode(src: &[u8]) -> ~[u8] {
     match decode_result(src) {
@@ -50,12 +194,80 @@ pub fn decode_result(src: &[u8]) -> Result<~[u8], ~str> {
     let dst_length = src.len() / 2;
     let mut dst = vec::with_capacity(dst_length);
     unsafe { vec::raw::set_len(&mut dst, dst_length); }
-    match base16_decode(BASE16_DECODE_MAP, dst, src) {
-        Done(_) => Ok(dst),
+    match base16_decode(BASE16_DECODE_MAP, dst, Some(src), false) {
+        Done(_) | Next(_) => Ok(dst),
         Fail(_, reason) => Err(reason)
     }
 }
 
+/// Decodes the string `src`, failing on bad input.
+pub fn decode_str(src: &str) -> ~[u8] {
+    let codec = &Standard as &Codec;
+    codec.decode_str(src)
+}
+
+/// Decodes the string `src` into a newly allocated vector.
+pub fn decode_str_result(src: &str) -> Result<~[u8], ~str> {
+    let codec = &Standard as &Codec;
+    codec.decode_str_result(src)
+}
+
+/// Encodes the first `n` bytes of `buf` over itself and returns the encoded
+/// length. `buf` must be at least `2 * n` bytes long.
+pub fn encode_in_place(buf: &mut [u8], n: uint) -> uint {
+    let codec = &Standard as &Codec;
+    codec.encode_in_place(buf, n)
+}
+
+/// Decodes the encoded text in `buf` over itself and returns the decoded
+/// length. On failure, returns the number of bytes decoded before the error
+/// along with the reason, and `buf` starts with those bytes.
+pub fn decode_in_place(buf: &mut [u8]) -> Result<uint, (uint, ~str)> {
+    let codec = &Standard as &Codec;
+    codec.decode_in_place(buf)
+}
+
+/// Decodes `src` into `dst`, failing unless it decodes to exactly
+/// `dst.len()` bytes.
+pub fn decode_exact(dst: &mut [u8], src: &[u8]) -> Result<(), ~str> {
+    let codec = &Standard as &Codec;
+    codec.decode_exact(dst, src)
+}
+
+/// Encodes `src` into the start of `dst` and returns the encoded text.
+pub fn encode_to_str<'a>(dst: &'a mut [u8], src: &[u8]) -> &'a str {
+    let codec = &Standard as &Codec;
+    codec.encode_to_str(dst, src)
+}
+
+/// Checks `src` without producing any output and returns its decoded
+/// length.
+pub fn validate(src: &[u8]) -> Result<uint, ~str> {
+    let codec = &Standard as &Codec;
+    codec.validate(src)
+}
+
+/// Returns true if `src` is well-formed.
+pub fn is_valid(src: &[u8]) -> bool {
+    let codec = &Standard as &Codec;
+    codec.is_valid(src)
+}
+
+/// Decodes `src` into a newly allocated vector. On failure, returns the
+/// bytes decoded up to the last complete valid quantum along with the
+/// reason.
+pub fn decode_partial(src: &[u8]) -> Result<~[u8], (~[u8], ~str)> {
+    let codec = &Standard as &Codec;
+    codec.decode_partial(src)
+}
+
+fn decode_partial_to(dst: &mut [u8], src: &[u8]) -> Result<uint, (uint, ~str)> {
+    match base16_decode(BASE16_DECODE_MAP, dst, Some(src), false) {
+        Done(n) | Next(n) => Ok(n),
+        Fail(n, reason) => Err((n, reason))
+    }
+}
+
 fn base16_encode(table: &[u8], dst: &mut [u8], src: &[u8]) {
     for i in range(0u, src.len()) {
         dst[i+1*i] = table[src[i]>>4];
@@ -63,20 +275,41 @@ fn base16_encode(table: &[u8], dst: &mut [u8], src: &[u8]) {
     }
 }
 
-fn base16_decode(decode_map: &[u8], dst: &mut [u8], src: &[u8]) -> DecodeSize {
-    if src.len() % 2 == 1 {
-        return Fail(0, ~"odd length base16 data");
+fn base16_decode(decode_map: &[u8], dst: &mut [u8], src: Option<&[u8]>,
+                 validate_only: bool) -> DecodeSize {
+    let len = match src { Some(src) => src.len(), None => dst.len() };
+    if len % 2 == 1 {
+        // decode the complete pairs anyway so that callers can recover them,
+        // but report the length as the reason, as it is checked first
+        let head = len - 1;
+        let result = match src {
+            Some(src) => base16_decode(decode_map, dst, Some(src.slice_to(head)), validate_only),
+            None => base16_decode(decode_map, dst.mut_slice_to(head), None, validate_only)
+        };
+        let ndecoded = match result {
+            Done(n) | Next(n) | Fail(n, _) => n
+        };
+        return Fail(ndecoded, ~"odd length base16 data");
     }
 
-    for i in range(0, src.len()/2) {
-        let a = decode_map[src[i*2]];
-        if (a == 0xff) { return Fail(0, fail_decode_on(src[i*2])); }
-        let b = decode_map[src[i*2+1]];
-        if (b == 0xff) { return Fail(0, fail_decode_on(src[i*2+1])); }
-        dst[i] = a<<4 | b;
+    for i in range(0, len/2) {
+        let c = input_at(dst, src, i*2);
+        let d = input_at(dst, src, i*2+1);
+        let a = decode_map[c];
+        if (a == 0xff) { return Fail(i, fail_decode_on(c)); }
+        let b = decode_map[d];
+        if (b == 0xff) { return Fail(i, fail_decode_on(d)); }
+        if !validate_only {
+            if i >= dst.len() { return Fail(i, fail_short_dst(dst.len())); }
+            dst[i] = a<<4 | b;
+        }
     }
 
-    Done(src.len()/2)
+    Done(len/2)
+}
+
+fn fail_short_dst(n: uint) -> ~str {
+    format!("output buffer of {} bytes is too small", n)
 }
 
 fn fail_decode_on(b: u8) -> ~str {
@@ -2,10 +2,17 @@
 
 use std::vec;
 
+use codec::{Codec, FromDecoded};
+use display::Display;
+use util::{DecodeSize, Done, Next, Fail, input_at};
+
 // TODO: doc
+#[deriving(Eq, Clone)]
 pub enum Base32Type {
     Standard,
     Hex,
+    Crockford,
+    ZBase32,
 }
 
 impl Base32Type {
@@ -21,22 +28,354 @@ impl Base32Type {
     pub fn decode_result(self, src: &[u8]) -> Result<~[u8], ~str> {
         decode_result(src, self)
     }
+    /// Decodes padded segments that were encoded separately and joined,
+    /// such as `MY======MZXQ====` for `f` and `fo`. Where `decode` stops
+    /// with "trailing data after padding", this carries on with the next
+    /// segment. The unpadded `Crockford` and `ZBase32` have no segment
+    /// boundary to find and decode as usual.
+    pub fn decode_concat(self, src: &[u8]) -> ~[u8] {
+        decode_concat(src, self)
+    }
+    /// Like `decode_concat`, but returns an error instead of failing on
+    /// bad input.
+    pub fn decode_concat_result(self, src: &[u8]) -> Result<~[u8], ~str> {
+        decode_concat_result(src, self)
+    }
+    /// Decodes `src` into a newly allocated vector. On failure, returns the
+    /// bytes decoded up to the last complete valid quantum along with the
+    /// reason.
+    pub fn decode_partial(self, src: &[u8]) -> Result<~[u8], (~[u8], ~str)> {
+        let codec = &self as &Codec;
+        codec.decode_partial(src)
+    }
+    /// Encodes the first `n` bytes of `buf` over itself and returns the
+    /// encoded length. `buf` must be at least `encoded_len(n)` bytes long.
+    pub fn encode_in_place(self, buf: &mut [u8], n: uint) -> uint {
+        let codec = &self as &Codec;
+        codec.encode_in_place(buf, n)
+    }
+    /// Decodes the encoded text in `buf` over itself and returns the decoded
+    /// length. On failure, returns the number of bytes decoded up to the
+    /// last complete valid quantum along with the reason, and `buf` starts
+    /// with those bytes.
+    pub fn decode_in_place(self, buf: &mut [u8]) -> Result<uint, (uint, ~str)> {
+        let codec = &self as &Codec;
+        codec.decode_in_place(buf)
+    }
+    /// Checks `src` without producing any output and returns its decoded
+    /// length.
+    pub fn validate(self, src: &[u8]) -> Result<uint, ~str> {
+        let codec = &self as &Codec;
+        codec.validate(src)
+    }
+    /// Returns true if `src` is well-formed.
+    pub fn is_valid(self, src: &[u8]) -> bool {
+        let codec = &self as &Codec;
+        codec.is_valid(src)
+    }
+    /// Encodes `src` into a newly allocated string.
+    pub fn encode_to_string(self, src: &[u8]) -> ~str {
+        let codec = &self as &Codec;
+        codec.encode_to_string(src)
+    }
+    /// Decodes the string `src`, failing on bad input.
+    pub fn decode_str(self, src: &str) -> ~[u8] {
+        let codec = &self as &Codec;
+        codec.decode_str(src)
+    }
+    /// Decodes the string `src` into a newly allocated vector.
+    pub fn decode_str_result(self, src: &str) -> Result<~[u8], ~str> {
+        let codec = &self as &Codec;
+        codec.decode_str_result(src)
+    }
+    /// Formats `src` as base32 without allocating, e.g.
+    /// `format!("{}", base32::Crockford.display(id))`.
+    pub fn display<'a>(self, src: &'a [u8]) -> Display<'a, Base32Type> {
+        Display::new(self, src)
+    }
+}
+
+impl Codec for Base32Type {
+    fn encoded_len(&self, n: uint) -> uint {
+        match *self {
+            Crockford | ZBase32 => (n * 8 + 4) / 5,
+            _ => (n + 4) / 5 * 8
+        }
+    }
+    fn block_len(&self) -> (uint, uint) {
+        (5, 8)
+    }
+    fn decoded_len(&self, n: uint) -> uint {
+        match *self {
+            Crockford | ZBase32 => n * 5 / 8,
+            _ => n / 8 * 5
+        }
+    }
+    fn encode_to(&self, dst: &mut [u8], src: &[u8]) {
+        encode_to(dst, src, *self)
+    }
+    fn decode_partial_to(&self, dst: &mut [u8], src: &[u8]) -> Result<uint, (uint, ~str)> {
+        decode_partial_to(dst, src, *self)
+    }
+    fn is_ignored(&self, c: u8) -> bool {
+        *self == Crockford && BASE32_CROCKFORD_DECODE_MAP[c] == SKIP
+    }
+    fn validate(&self, src: &[u8]) -> Result<uint, ~str> {
+        match decode_size(&mut [], Some(src), *self, false, true) {
+            Done(n) | Next(n) => Ok(n),
+            Fail(_, reason) => Err(reason)
+        }
+    }
+    fn decode_in_place(&self, buf: &mut [u8]) -> Result<uint, (uint, ~str)> {
+        match decode_size(buf, None, *self, false, false) {
+            Done(n) | Next(n) => Ok(n),
+            Fail(n, reason) => Err((n, reason))
+        }
+    }
+}
+
+fn decode_concat(src: &[u8], base32_type: Base32Type) -> ~[u8] {
+    match decode_concat_result(src, base32_type) {
+        Ok(dst) => dst,
+        Err(reason) => fail!(reason)
+    }
+}
+
+fn decode_concat_result(src: &[u8], base32_type: Base32Type) -> Result<~[u8], ~str> {
+    let dst_length = base32_type.decoded_len(src.len());
+    let mut dst = vec::with_capacity(dst_length);
+
+    unsafe {
+        vec::raw::set_len(&mut dst, dst_length);
+    }
+
+    match decode_size(dst, Some(src), base32_type, true, false) {
+        Done(n) => unsafe { vec::raw::set_len(&mut dst, n) },
+        Next(n) => unsafe { vec::raw::set_len(&mut dst, n) },
+        Fail(_, reason) => return Err(reason)
+    }
+
+    Ok(dst)
+}
+
+fn decode_size(dst: &mut [u8], src: Option<&[u8]>, base32_type: Base32Type,
+               concat: bool, validate_only: bool) -> DecodeSize {
+    match base32_type {
+        Standard => base32_decode(BASE32_STANDARD_DECODE_MAP, dst, src, true, concat, validate_only),
+        Hex => base32_decode(BASE32_HEX_DECODE_MAP, dst, src, true, concat, validate_only),
+        Crockford => base32_decode(BASE32_CROCKFORD_DECODE_MAP, dst, src, false, false, validate_only),
+        ZBase32 => base32_decode(ZBASE32_DECODE_MAP, dst, src, false, false, validate_only)
+    }
+}
+
+/// Encodes the first `nbits` bits of `src` with `ZBase32`, producing
+/// `(nbits + 4) / 5` characters.
+pub fn zbase32_encode_bits(src: &[u8], nbits: uint) -> ~[u8] {
+    assert!(nbits <= src.len() * 8);
+    let nbytes = (nbits + 7) / 8;
+    let mut bytes = src.slice_to(nbytes).to_owned();
+    if nbits % 8 != 0 {
+        bytes[nbytes - 1] &= 0xff_u8 << (8 - nbits % 8);
+    }
+
+    let mut dst = encode(bytes, ZBase32);
+    dst.truncate((nbits + 4) / 5);
+    dst
+}
+
+/// Decodes `ZBase32` text carrying `nbits` bits, which must be exactly
+/// `(nbits + 4) / 5` characters long. Bits past `nbits` are cleared.
+pub fn zbase32_decode_bits(src: &[u8], nbits: uint) -> Result<~[u8], ~str> {
+    if src.len() != (nbits + 4) / 5 {
+        return Err(format!("expected {} characters for {} bits, got {}",
+                           (nbits + 4) / 5, nbits, src.len()));
+    }
+
+    // fill up the final quantum with zero bits so that any length decodes
+    let mut src = src.to_owned();
+    while src.len() % 8 != 0 {
+        src.push(ZBASE32_TABLE[0]);
+    }
+
+    let nbytes = (nbits + 7) / 8;
+    let mut dst = match decode_result(src, ZBase32) {
+        Ok(dst) => dst,
+        Err(reason) => return Err(reason)
+    };
+    dst.truncate(nbytes);
+    if nbits % 8 != 0 {
+        dst[nbytes - 1] &= 0xff_u8 << (8 - nbits % 8);
+    }
+
+    Ok(dst)
+}
+
+/// Splits `src` into 5-bit groups, most significant bits first, one group
+/// per output byte. The last group is filled up with zero bits.
+pub fn to_quintets(src: &[u8]) -> ~[u8] {
+    let dst_length = (src.len() * 8 + 4) / 5;
+    let mut dst = vec::from_elem(dst_length, 0u8);
+    base32_encode_unpadded(QUINTET_TABLE, dst, src);
+    dst
+}
+
+/// Joins 5-bit groups back into bytes, reversing `to_quintets`. Fails on
+/// groups above 31, on 5 or more leftover bits and on non-zero fill bits.
+pub fn from_quintets(src: &[u8]) -> Result<~[u8], ~str> {
+    let dst_length = src.len() * 5 / 8;
+    let mut dst = vec::from_elem(dst_length, 0u8);
+    match base32_decode(QUINTET_DECODE_MAP, dst, Some(src), false, false, false) {
+        Fail(_, reason) => return Err(reason),
+        _ => ()
+    }
+
+    let nfill = src.len() * 5 % 8;
+    if nfill > 0 && src[src.len() - 1] & ((1 << nfill) - 1) != 0 {
+        return Err(~"non-zero fill bits in the last 5-bit group");
+    }
+
+    Ok(dst)
+}
+
+/// Computes the Crockford check symbol of `src`, taken as a big-endian
+/// number.
+pub fn crockford_check_symbol(src: &[u8]) -> u8 {
+    let mut n = 0u;
+    for &b in src.iter() {
+        n = (n * 256 + b as uint) % 37;
+    }
+    CROCKFORD_CHECK_SYMBOLS[n]
+}
+
+/// Encodes `src` with `Crockford` and appends its check symbol.
+pub fn crockford_encode_check(src: &[u8]) -> ~[u8] {
+    let mut dst = encode(src, Crockford);
+    dst.push(crockford_check_symbol(src));
+    dst
+}
+
+/// Decodes `Crockford` text whose last character is a check symbol, and
+/// verifies the symbol against the decoded bytes.
+pub fn crockford_decode_check(src: &[u8]) -> Result<~[u8], ~str> {
+    if src.len() == 0 {
+        return Err(~"missing check symbol");
+    }
+    let check = src[src.len() - 1];
+    let dst = match decode_result(src.slice_to(src.len() - 1), Crockford) {
+        Ok(dst) => dst,
+        Err(reason) => return Err(reason)
+    };
+    let expect = crockford_check_symbol(dst);
+    match crockford_check_value(check) {
+        Some(n) if CROCKFORD_CHECK_SYMBOLS[n] == expect => Ok(dst),
+        Some(_) => Err(format!("check symbol mismatch: expected {}, found {}",
+                               expect as char, check as char)),
+        None => Err(format!("illegal check symbol {}", check))
+    }
+}
+
+fn crockford_check_value(c: u8) -> Option<uint> {
+    match c {
+        42       => Some(32), // '*'
+        126      => Some(33), // '~'
+        36       => Some(34), // '$'
+        61       => Some(35), // '='
+        85 | 117 => Some(36), // 'U', 'u'
+        _ => match BASE32_CROCKFORD_DECODE_MAP[c] {
+            0xff | SKIP => None,
+            n => Some(n as uint)
+        }
+    }
+}
+
+/// Conversion of raw bytes to base32 text, e.g. `data.to_base32(base32::Hex)`.
+pub trait ToBase32 {
+    fn to_base32(&self, base32_type: Base32Type) -> ~str;
+}
+
+/// Conversion of base32 text to raw bytes, e.g. `text.from_base32(base32::Standard)`.
+pub trait FromBase32 {
+    fn from_base32(&self, base32_type: Base32Type) -> Result<~[u8], ~str>;
+
+    /// Decodes straight into a type such as a key or token.
+    fn from_base32_into<T: FromDecoded>(&self, base32_type: Base32Type) -> Result<T, ~str> {
+        match self.from_base32(base32_type) {
+            Ok(bytes) => FromDecoded::from_decoded(bytes),
+            Err(reason) => Err(reason)
+        }
+    }
+}
+
+impl<'a> ToBase32 for &'a [u8] {
+    fn to_base32(&self, base32_type: Base32Type) -> ~str {
+        base32_type.encode_to_string(*self)
+    }
+}
+
+impl ToBase32 for ~[u8] {
+    fn to_base32(&self, base32_type: Base32Type) -> ~str {
+        base32_type.encode_to_string(self.as_slice())
+    }
+}
+
+impl<'a> FromBase32 for &'a str {
+    fn from_base32(&self, base32_type: Base32Type) -> Result<~[u8], ~str> {
+        decode_result(self.as_bytes(), base32_type)
+    }
 }
 
-enum DecodeSize {
-    Done(uint),       // on uncontinuable state
-    Next(uint),       // on continuable state
-    Fail(uint, ~str), // on decode failure
+impl FromBase32 for ~str {
+    fn from_base32(&self, base32_type: Base32Type) -> Result<~[u8], ~str> {
+        decode_result(self.as_bytes(), base32_type)
+    }
 }
 
 static PAD: u8 = 61; // '='
 
+// Marks characters in a decode map that are skipped, such as the hyphens
+// Crockford allows for readability.
+static SKIP: u8 = 0xfe;
+
 static BASE32_STANDARD_TABLE: &'static [u8] =
     bytes!("ABCDEFGHIJKLMNOPQRSTUVWXYZ234567");
 
 static BASE32_HEX_TABLE: &'static [u8] =
     bytes!("0123456789ABCDEFGHIJKLMNOPQRSTUV");
 
+static BASE32_CROCKFORD_TABLE: &'static [u8] =
+    bytes!("0123456789ABCDEFGHJKMNPQRSTVWXYZ");
+
+static ZBASE32_TABLE: &'static [u8] =
+    bytes!("ybndrfg8ejkmcpqxot1uwisza345h769");
+
+static CROCKFORD_CHECK_SYMBOLS: &'static [u8] =
+    bytes!("0123456789ABCDEFGHJKMNPQRSTVWXYZ*~$=U");
+
+// Maps every 5-bit group to itself, for `to_quintets` and `from_quintets`.
+static QUINTET_TABLE: [u8, ..32] = [
+      0,   1,   2,   3,   4,   5,   6,   7,   8,   9,  10,  11,  12,  13,  14,  15,
+     16,  17,  18,  19,  20,  21,  22,  23,  24,  25,  26,  27,  28,  29,  30,  31,
+];
+
+static QUINTET_DECODE_MAP: [u8, ..256] = [
+      0,   1,   2,   3,   4,   5,   6,   7,   8,   9,  10,  11,  12,  13,  14,  15,
+     16,  17,  18,  19,  20,  21,  22,  23,  24,  25,  26,  27,  28,  29,  30,  31,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+];
+
 static BASE32_STANDARD_DECODE_MAP: [u8, ..256] = [
     255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
     255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
@@ -75,18 +414,54 @@ static BASE32_HEX_DECODE_MAP: [u8, ..256] = [
     255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
 ];
 
+// Case-insensitive, with O read as 0 and I, L read as 1, and `-` skipped.
+static BASE32_CROCKFORD_DECODE_MAP: [u8, ..256] = [
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 254, 255, 255,
+      0,   1,   2,   3,   4,   5,   6,   7,   8,   9, 255, 255, 255, 255, 255, 255,
+    255,  10,  11,  12,  13,  14,  15,  16,  17,   1,  18,  19,   1,  20,  21,   0,
+     22,  23,  24,  25,  26, 255,  27,  28,  29,  30,  31, 255, 255, 255, 255, 255,
+    255,  10,  11,  12,  13,  14,  15,  16,  17,   1,  18,  19,   1,  20,  21,   0,
+     22,  23,  24,  25,  26, 255,  27,  28,  29,  30,  31, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+];
+
+static ZBASE32_DECODE_MAP: [u8, ..256] = [
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255,  18, 255,  25,  26,  27,  30,  29,   7,  31, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255,  24,   1,  12,   3,   8,   5,   6,  28,  21,   9,  10, 255,  11,   2,  16,
+     13,  14,   4,  22,  17,  19, 255,  20,  15,   0,  23, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+];
+
 fn encode(src: &[u8], base32_type: Base32Type) -> ~[u8] {
-    let dst_length = (src.len() + 4) / 5 * 8;
+    let dst_length = base32_type.encoded_len(src.len());
     let mut dst = vec::with_capacity(dst_length);
 
     unsafe {
         vec::raw::set_len(&mut dst, dst_length);
     }
 
-    match base32_type {
-        Standard => base32_encode(BASE32_STANDARD_TABLE, dst, src),
-        Hex => base32_encode(BASE32_HEX_TABLE, dst, src)
-    }
+    encode_to(dst, src, base32_type);
 
     dst
 }
@@ -99,19 +474,14 @@ fn decode(src: &[u8], base32_type: Base32Type) -> ~[u8] {
 }
 
 fn decode_result(src: &[u8], base32_type: Base32Type) -> Result<~[u8], ~str> {
-    let dst_length = src.len() / 8 * 5;
+    let dst_length = base32_type.decoded_len(src.len());
     let mut dst = vec::with_capacity(dst_length);
 
     unsafe {
         vec::raw::set_len(&mut dst, dst_length);
     }
 
-    let size = match base32_type {
-        Standard => base32_decode(BASE32_STANDARD_DECODE_MAP, dst, src),
-        Hex => base32_decode(BASE32_HEX_DECODE_MAP, dst, src)
-    };
-
-    match size {
+    match decode_size(dst, Some(src), base32_type, false, false) {
         Done(n) => unsafe { vec::raw::set_len(&mut dst, n) },
         Next(n) => unsafe { vec::raw::set_len(&mut dst, n) },
         Fail(_, reason) => return Err(reason)
@@ -120,6 +490,23 @@ fn decode_result(src: &[u8], base32_type: Base32Type) -> Result<~[u8], ~str> {
     Ok(dst)
 }
 
+fn encode_to(dst: &mut [u8], src: &[u8], base32_type: Base32Type) {
+    match base32_type {
+        Standard => base32_encode(BASE32_STANDARD_TABLE, dst, src),
+        Hex => base32_encode(BASE32_HEX_TABLE, dst, src),
+        Crockford => base32_encode_unpadded(BASE32_CROCKFORD_TABLE, dst, src),
+        ZBase32 => base32_encode_unpadded(ZBASE32_TABLE, dst, src)
+    }
+}
+
+fn decode_partial_to(dst: &mut [u8], src: &[u8], base32_type: Base32Type) -> Result<uint, (uint, ~str)> {
+    match decode_size(dst, Some(src), base32_type, false, false) {
+        Done(n) => Ok(n),
+        Next(n) => Ok(n),
+        Fail(n, reason) => Err((n, reason))
+    }
+}
+
 fn base32_encode(table: &[u8], dst: &mut [u8], src: &[u8]) {
     let len = src.len();
 
@@ -145,14 +532,45 @@ fn base32_encode(table: &[u8], dst: &mut [u8], src: &[u8]) {
     }
 }
 
-fn base32_decode(decode_map: &[u8], dst: &mut [u8], src: &[u8]) -> DecodeSize {
-    let len = src.len();
+// Like `base32_encode`, but leaves out the padding of the final quantum.
+fn base32_encode_unpadded(table: &[u8], dst: &mut [u8], src: &[u8]) {
+    let full = src.len() / 5 * 5;
+    base32_encode(table, dst, src.slice_to(full));
+
+    if full < src.len() {
+        let mut quantum = [0u8, ..8];
+        base32_encode(table, quantum, src.slice_from(full));
+        let dst = dst.mut_slice_from(full / 5 * 8);
+        for i in range(0, dst.len()) {
+            dst[i] = quantum[i];
+        }
+    }
+}
+
+// Without `padded`, the final quantum may be short and `=` is not accepted.
+// With `concat`, decoding resumes after a padded quantum instead of failing
+// on trailing data.
+fn base32_decode(decode_map: &[u8], dst: &mut [u8], src: Option<&[u8]>,
+                 padded: bool, concat: bool, validate_only: bool) -> DecodeSize {
+    let len = match src { Some(src) => src.len(), None => dst.len() };
 
     if len == 0 {
         return Done(0);
     }
-    if len < 8 || (len % 8) != 0 {
-        return Fail(0, ~"the input length should be divisible by 8");
+    if padded && (len < 8 || (len % 8) != 0) {
+        // decode the complete quanta anyway so that callers can recover them,
+        // but report the length as the reason, as it is checked first
+        let head = len / 8 * 8;
+        let result = match src {
+            Some(src) => base32_decode(decode_map, dst, Some(src.slice_to(head)),
+                                       padded, concat, validate_only),
+            None => base32_decode(decode_map, dst.mut_slice_to(head), None,
+                                  padded, concat, validate_only)
+        };
+        let ndecoded = match result {
+            Done(n) | Next(n) | Fail(n, _) => n
+        };
+        return Fail(ndecoded, ~"the input length should be divisible by 8");
     }
 
     let mut end = false;
@@ -160,23 +578,36 @@ fn base32_decode(decode_map: &[u8], dst: &mut [u8], src: &[u8]) -> DecodeSize {
     let mut ndecoded = 0;
     let mut i = 0;
 
-    while leftover > 0 && !end {
+    while leftover > 0 {
+        if end {
+            if !concat {
+                return Fail(ndecoded, fail_trailing_at(len - leftover));
+            }
+            end = false;
+        }
+
         let mut buf = [0xff_u8, ..8];
         let mut buf_len = 8u;
 
+        // the quantum starts `j + skipped` bytes back, counting hyphens
         let mut j = 0u;
+        let mut skipped = 0u;
         while j < 8 {
             if leftover == 0 {
-                return Fail(ndecoded, fail_decode_at(len - leftover - j));
+                if padded || j == 1 || j == 3 || j == 6 {
+                    return Fail(ndecoded, fail_decode_at(len - leftover - j - skipped));
+                }
+                buf_len = j;
+                break;
             }
-            let c = src[len - leftover];
+            let c = input_at(dst, src, len - leftover);
             leftover -= 1;
-            if c == PAD && j >= 2 && leftover < 8 {
+            if padded && c == PAD && j >= 2 {
                 if leftover + j < 8 - 1 {
                     return Fail(ndecoded, fail_decode_at(len));
                 }
                 for k in range(0u, 8 - 1 - j) {
-                    if leftover > k && src[len-leftover+k] != PAD {
+                    if leftover > k && input_at(dst, src, len-leftover+k) != PAD {
                         return Fail(ndecoded, fail_decode_at(len - leftover + k - 1));
                     }
                 }
@@ -185,44 +616,68 @@ fn base32_decode(decode_map: &[u8], dst: &mut [u8], src: &[u8]) -> DecodeSize {
                 if buf_len == 1 || buf_len == 3 || buf_len == 6 {
                     return Fail(ndecoded, fail_decode_at(len - leftover - 1));
                 }
+                leftover -= 8 - 1 - j;
                 break;
             }
             buf[j] = decode_map[c];
+            if buf[j] == SKIP {
+                skipped += 1;
+                continue;
+            }
             if buf[j] == 0xff {
                 return Fail(ndecoded, fail_decode_at(len - leftover - 1));
             }
             j += 1;
         }
 
-        dst[i] = 0;
-        dst[i+1] = 0;
-        dst[i+2] = 0;
-        dst[i+3] = 0;
-        dst[i+4] = 0;
-
-        dst[i] |= buf[0]<<3 | buf[1]>>2;
-        dst[i+1] |= if buf_len > 2 { buf[1]<<6 | buf[2]<<1 } else { 0 };
-        dst[i+1] |= if buf_len > 3 { buf[3]>>4             } else { 0 };
-        dst[i+2] |= if buf_len > 3 { buf[3]<<4             } else { 0 };
-        dst[i+2] |= if buf_len > 4 { buf[4]>>1             } else { 0 };
-        dst[i+3] |= if buf_len > 4 { buf[4]<<7 | buf[5]<<2 } else { 0 };
-        dst[i+3] |= if buf_len > 6 { buf[6]>>3             } else { 0 };
-        dst[i+4] |= if buf_len > 6 { buf[6]<<5 | buf[7]    } else { 0 };
-
-        i += 5;
-        match buf_len {
-            2     => ndecoded += 1,
-            3 | 4 => ndecoded += 2,
-            5     => ndecoded += 3,
-            6 | 7 => ndecoded += 4,
-            8     => ndecoded += 5,
+        let nbytes = match buf_len {
+            0     => 0,
+            2     => 1,
+            3 | 4 => 2,
+            5     => 3,
+            6 | 7 => 4,
+            8     => 5,
             _     => fail!(~"malformed base32 string")
+        };
+
+        if !validate_only {
+            if i + nbytes > dst.len() {
+                return Fail(ndecoded, fail_short_dst(dst.len()));
+            }
+
+            // a short final quantum decodes to fewer than 5 bytes, and `dst`
+            // may have no room for the rest
+            let mut out = [0u8, ..5];
+
+            out[0] |= buf[0]<<3 | buf[1]>>2;
+            out[1] |= if buf_len > 2 { buf[1]<<6 | buf[2]<<1 } else { 0 };
+            out[1] |= if buf_len > 3 { buf[3]>>4             } else { 0 };
+            out[2] |= if buf_len > 3 { buf[3]<<4             } else { 0 };
+            out[2] |= if buf_len > 4 { buf[4]>>1             } else { 0 };
+            out[3] |= if buf_len > 4 { buf[4]<<7 | buf[5]<<2 } else { 0 };
+            out[3] |= if buf_len > 6 { buf[6]>>3             } else { 0 };
+            out[4] |= if buf_len > 6 { buf[6]<<5 | buf[7]    } else { 0 };
+
+            for k in range(0, nbytes) {
+                dst[i+k] = out[k];
+            }
         }
+
+        ndecoded += nbytes;
+        i = ndecoded;
     }
 
     if end { Done(ndecoded) } else { Next(ndecoded) }
 }
 
+fn fail_short_dst(n: uint) -> ~str {
+    format!("output buffer of {} bytes is too small", n)
+}
+
 fn fail_decode_at(n: uint) -> ~str {
     format!("illegal base32 data at input byte {}", n)
 }
+
+fn fail_trailing_at(n: uint) -> ~str {
+    format!("trailing data after padding at input byte {}", n)
+}
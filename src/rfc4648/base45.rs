@@ -0,0 +1,242 @@
+// base45.rs
+
+use codec::Codec;
+use util::{DecodeSize, Done, Next, Fail, input_at};
+
+/// The RFC 9285 encoding, designed for the alphanumeric mode of QR codes.
+#[deriving(Eq, Clone)]
+pub enum Base45Type {
+    Standard,
+}
+
+impl Base45Type {
+    /// Encodes `src` into a newly allocated vector.
+    pub fn encode(self, src: &[u8]) -> ~[u8] {
+        encode(src)
+    }
+    /// Decodes `src` into a newly allocated vector, failing on bad input.
+    pub fn decode(self, src: &[u8]) -> ~[u8] {
+        decode(src)
+    }
+    /// Decodes `src` into a newly allocated vector.
+    pub fn decode_result(self, src: &[u8]) -> Result<~[u8], ~str> {
+        decode_result(src)
+    }
+    /// Checks `src` without producing any output and returns its decoded
+    /// length.
+    pub fn validate(self, src: &[u8]) -> Result<uint, ~str> {
+        validate(src)
+    }
+    /// Returns true if `src` is well-formed.
+    pub fn is_valid(self, src: &[u8]) -> bool {
+        is_valid(src)
+    }
+    /// Encodes `src` into a newly allocated string.
+    pub fn encode_to_string(self, src: &[u8]) -> ~str {
+        encode_to_string(src)
+    }
+    /// Decodes the string `src` into a newly allocated vector.
+    pub fn decode_str_result(self, src: &str) -> Result<~[u8], ~str> {
+        let codec = &self as &Codec;
+        codec.decode_str_result(src)
+    }
+}
+
+impl Codec for Base45Type {
+    fn encoded_len(&self, n: uint) -> uint {
+        encoded_len(n)
+    }
+    fn block_len(&self) -> (uint, uint) {
+        (2, 3)
+    }
+    fn decoded_len(&self, n: uint) -> uint {
+        decoded_len(n)
+    }
+    fn encode_to(&self, dst: &mut [u8], src: &[u8]) {
+        base45_encode(BASE45_TABLE, dst, src)
+    }
+    fn decode_partial_to(&self, dst: &mut [u8], src: &[u8]) -> Result<uint, (uint, ~str)> {
+        decode_partial_to(dst, src)
+    }
+    fn validate(&self, src: &[u8]) -> Result<uint, ~str> {
+        match base45_decode(BASE45_DECODE_MAP, &mut [], Some(src), true) {
+            Done(n) | Next(n) => Ok(n),
+            Fail(_, reason) => Err(reason)
+        }
+    }
+    fn decode_in_place(&self, buf: &mut [u8]) -> Result<uint, (uint, ~str)> {
+        match base45_decode(BASE45_DECODE_MAP, buf, None, false) {
+            Done(n) | Next(n) => Ok(n),
+            Fail(n, reason) => Err((n, reason))
+        }
+    }
+}
+
+static BASE45_TABLE: &'static [u8] = bytes!("0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:");
+
+static BASE45_DECODE_MAP: [u8, ..256] = [
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+     36, 255, 255, 255,  37,  38, 255, 255, 255, 255,  39,  40, 255,  41,  42,  43,
+      0,   1,   2,   3,   4,   5,   6,   7,   8,   9,  44, 255, 255, 255, 255, 255,
+    255,  10,  11,  12,  13,  14,  15,  16,  17,  18,  19,  20,  21,  22,  23,  24,
+     25,  26,  27,  28,  29,  30,  31,  32,  33,  34,  35, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+];
+
+/// Encodes `src` into a newly allocated vector.
+pub fn encode(src: &[u8]) -> ~[u8] {
+    let codec = &Standard as &Codec;
+    codec.encode(src)
+}
+
+/// Encodes `src` into a newly allocated string.
+pub fn encode_to_string(src: &[u8]) -> ~str {
+    let codec = &Standard as &Codec;
+    codec.encode_to_string(src)
+}
+
+/// Decodes `src` into a newly allocated vector, failing on bad input.
+pub fn decode(src: &[u8]) -> ~[u8] {
+    let codec = &Standard as &Codec;
+    codec.decode(src)
+}
+
+/// Decodes `src` into a newly allocated vector.
+pub fn decode_result(src: &[u8]) -> Result<~[u8], ~str> {
+    let codec = &Standard as &Codec;
+    codec.decode_result(src)
+}
+
+/// Checks `src` without producing any output and returns its decoded
+/// length.
+pub fn validate(src: &[u8]) -> Result<uint, ~str> {
+    let codec = &Standard as &Codec;
+    codec.validate(src)
+}
+
+/// Returns true if `src` is well-formed.
+pub fn is_valid(src: &[u8]) -> bool {
+    let codec = &Standard as &Codec;
+    codec.is_valid(src)
+}
+
+/// Decodes the encoded text in `buf` over itself and returns the decoded
+/// length. On failure, returns the number of bytes decoded before the error
+/// along with the reason, and `buf` starts with those bytes.
+pub fn decode_in_place(buf: &mut [u8]) -> Result<uint, (uint, ~str)> {
+    let codec = &Standard as &Codec;
+    codec.decode_in_place(buf)
+}
+
+fn encoded_len(n: uint) -> uint {
+    n / 2 * 3 + n % 2 * 2
+}
+
+fn decoded_len(n: uint) -> uint {
+    n / 3 * 2 + if n % 3 == 2 { 1 } else { 0 }
+}
+
+fn decode_partial_to(dst: &mut [u8], src: &[u8]) -> Result<uint, (uint, ~str)> {
+    match base45_decode(BASE45_DECODE_MAP, dst, Some(src), false) {
+        Done(n) | Next(n) => Ok(n),
+        Fail(n, reason) => Err((n, reason))
+    }
+}
+
+// Every 2 bytes form a big-endian number n, written as three digits c, d
+// and e with n = c + d * 45 + e * 45 * 45. A trailing byte is written as
+// two digits.
+fn base45_encode(table: &[u8], dst: &mut [u8], src: &[u8]) {
+    for i in range(0u, src.len() / 2) {
+        let n = src[i*2] as uint << 8 | src[i*2+1] as uint;
+        dst[i*3] = table[n % 45];
+        dst[i*3+1] = table[n / 45 % 45];
+        dst[i*3+2] = table[n / 2025];
+    }
+
+    if src.len() % 2 == 1 {
+        let n = src[src.len() - 1] as uint;
+        let j = src.len() / 2 * 3;
+        dst[j] = table[n % 45];
+        dst[j+1] = table[n / 45];
+    }
+}
+
+fn base45_decode(decode_map: &[u8], dst: &mut [u8], src: Option<&[u8]>,
+                 validate_only: bool) -> DecodeSize {
+    let len = match src { Some(src) => src.len(), None => dst.len() };
+    // a lone trailing character is an error, reported after the complete
+    // groups before it are decoded
+    let end = if len % 3 == 1 { len - 1 } else { len };
+
+    let mut ndecoded = 0;
+    let mut i = 0;
+    while i < end {
+        let k = if end - i >= 3 { 3 } else { 2 };
+
+        let mut n = 0u;
+        let mut scale = 1u;
+        for j in range(i, i + k) {
+            let v = decode_map[input_at(dst, src, j)];
+            if v == 0xff {
+                return Fail(ndecoded, fail_decode_at(j));
+            }
+            n += v as uint * scale;
+            scale *= 45;
+        }
+        if !validate_only && ndecoded + k - 1 > dst.len() {
+            return Fail(ndecoded, fail_short_dst(dst.len()));
+        }
+
+        if k == 3 {
+            if n > 0xffff {
+                return Fail(ndecoded, fail_range_at("triplet", i));
+            }
+            if !validate_only {
+                dst[ndecoded] = (n >> 8) as u8;
+                dst[ndecoded+1] = n as u8;
+            }
+            ndecoded += 2;
+        } else {
+            if n > 0xff {
+                return Fail(ndecoded, fail_range_at("pair", i));
+            }
+            if !validate_only { dst[ndecoded] = n as u8; }
+            ndecoded += 1;
+        }
+
+        i += k;
+    }
+
+    if end < len {
+        return Fail(ndecoded, fail_length(len));
+    }
+
+    Done(ndecoded)
+}
+
+fn fail_short_dst(n: uint) -> ~str {
+    format!("output buffer of {} bytes is too small", n)
+}
+
+fn fail_decode_at(n: uint) -> ~str {
+    format!("illegal base45 data at input byte {}", n)
+}
+
+fn fail_range_at(group: &str, n: uint) -> ~str {
+    format!("base45 {} out of range at input byte {}", group, n)
+}
+
+fn fail_length(n: uint) -> ~str {
+    format!("invalid base45 length {}", n)
+}
@@ -0,0 +1,264 @@
+// base58.rs
+
+use std::vec;
+use std::to_str::ToStr;
+
+use extra::crypto::digest::Digest;
+use extra::crypto::sha2::Sha256;
+
+use util;
+
+/// Base58 alphabets. `Bitcoin` is also the alphabet used by IPFS.
+#[deriving(Eq, Clone)]
+pub enum Base58Type {
+    Bitcoin,
+    Flickr,
+}
+
+impl Base58Type {
+    /// Encodes `src` into a newly allocated vector.
+    pub fn encode(self, src: &[u8]) -> ~[u8] {
+        encode(src, self)
+    }
+    /// Decodes `src` into a newly allocated vector, failing on bad input.
+    pub fn decode(self, src: &[u8]) -> ~[u8] {
+        decode(src, self)
+    }
+    /// Decodes `src` into a newly allocated vector.
+    pub fn decode_result(self, src: &[u8]) -> Result<~[u8], ~str> {
+        decode_result(src, self)
+    }
+    /// Encodes `src` into a newly allocated string.
+    pub fn encode_to_string(self, src: &[u8]) -> ~str {
+        encode_to_string(src, self)
+    }
+    /// Decodes the string `src` into a newly allocated vector.
+    pub fn decode_str_result(self, src: &str) -> Result<~[u8], ~str> {
+        decode_result(src.as_bytes(), self)
+    }
+    /// Encodes `version` and `payload` followed by a 4-byte double-SHA256
+    /// checksum, as in Base58Check.
+    pub fn encode_check(self, version: u8, payload: &[u8]) -> ~[u8] {
+        encode_check(version, payload, self)
+    }
+    /// Decodes Base58Check text and returns its version byte and payload.
+    pub fn decode_check(self, src: &[u8]) -> Result<(u8, ~[u8]), CheckError> {
+        decode_check(src, self)
+    }
+}
+
+/// Reasons for `decode_check` to fail.
+#[deriving(Eq, Clone)]
+pub enum CheckError {
+    /// The text is not valid base58.
+    InvalidBase58(~str),
+    /// The decoded data, of the given length, has no room for a version
+    /// byte and checksum.
+    TooShort(uint),
+    /// The checksum computed from the data and the one found in the text,
+    /// as big-endian numbers.
+    ChecksumMismatch(u32, u32),
+}
+
+impl ToStr for CheckError {
+    fn to_str(&self) -> ~str {
+        match *self {
+            InvalidBase58(ref reason) => reason.clone(),
+            TooShort(n) => format!("base58check data too short: {} bytes", n),
+            ChecksumMismatch(expect, found) =>
+                format!("base58check checksum mismatch: expected {:08x}, found {:08x}",
+                        expect, found)
+        }
+    }
+}
+
+static BASE58_BITCOIN_TABLE: &'static [u8] =
+    bytes!("123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz");
+
+static BASE58_FLICKR_TABLE: &'static [u8] =
+    bytes!("123456789abcdefghijkmnopqrstuvwxyzABCDEFGHJKLMNPQRSTUVWXYZ");
+
+static BASE58_BITCOIN_DECODE_MAP: [u8, ..256] = [
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255,   0,   1,   2,   3,   4,   5,   6,   7,   8, 255, 255, 255, 255, 255, 255,
+    255,   9,  10,  11,  12,  13,  14,  15,  16, 255,  17,  18,  19,  20,  21, 255,
+     22,  23,  24,  25,  26,  27,  28,  29,  30,  31,  32, 255, 255, 255, 255, 255,
+    255,  33,  34,  35,  36,  37,  38,  39,  40,  41,  42,  43, 255,  44,  45,  46,
+     47,  48,  49,  50,  51,  52,  53,  54,  55,  56,  57, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+];
+
+static BASE58_FLICKR_DECODE_MAP: [u8, ..256] = [
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255,   0,   1,   2,   3,   4,   5,   6,   7,   8, 255, 255, 255, 255, 255, 255,
+    255,  34,  35,  36,  37,  38,  39,  40,  41, 255,  42,  43,  44,  45,  46, 255,
+     47,  48,  49,  50,  51,  52,  53,  54,  55,  56,  57, 255, 255, 255, 255, 255,
+    255,   9,  10,  11,  12,  13,  14,  15,  16,  17,  18,  19, 255,  20,  21,  22,
+     23,  24,  25,  26,  27,  28,  29,  30,  31,  32,  33, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+];
+
+fn encode(src: &[u8], base58_type: Base58Type) -> ~[u8] {
+    match base58_type {
+        Bitcoin => base58_encode(BASE58_BITCOIN_TABLE, src),
+        Flickr => base58_encode(BASE58_FLICKR_TABLE, src)
+    }
+}
+
+fn encode_to_string(src: &[u8], base58_type: Base58Type) -> ~str {
+    util::encoded_to_string(encode(src, base58_type))
+}
+
+fn decode(src: &[u8], base58_type: Base58Type) -> ~[u8] {
+    match decode_result(src, base58_type) {
+        Ok(dst) => dst,
+        Err(reason) => fail!(reason)
+    }
+}
+
+fn decode_result(src: &[u8], base58_type: Base58Type) -> Result<~[u8], ~str> {
+    match base58_type {
+        Bitcoin => base58_decode(BASE58_BITCOIN_DECODE_MAP, src),
+        Flickr => base58_decode(BASE58_FLICKR_DECODE_MAP, src)
+    }
+}
+
+fn encode_check(version: u8, payload: &[u8], base58_type: Base58Type) -> ~[u8] {
+    let mut data = vec::with_capacity(payload.len() + 5);
+    data.push(version);
+    data.push_all(payload);
+    let check = checksum(data);
+    data.push_all(check);
+    encode(data, base58_type)
+}
+
+fn decode_check(src: &[u8], base58_type: Base58Type) -> Result<(u8, ~[u8]), CheckError> {
+    let data = match decode_result(src, base58_type) {
+        Ok(data) => data,
+        Err(reason) => return Err(InvalidBase58(reason))
+    };
+    if data.len() < 5 {
+        return Err(TooShort(data.len()));
+    }
+
+    let split = data.len() - 4;
+    let expect = be_u32(checksum(data.slice_to(split)));
+    let found = be_u32(data.slice_from(split));
+    if expect != found {
+        return Err(ChecksumMismatch(expect, found));
+    }
+
+    Ok((data[0], data.slice(1, split).to_owned()))
+}
+
+// The first four bytes of SHA256(SHA256(data)).
+fn checksum(data: &[u8]) -> [u8, ..4] {
+    let mut sha = Sha256::new();
+    let mut hash = [0u8, ..32];
+    sha.input(data);
+    sha.result(hash);
+    sha.reset();
+    sha.input(hash);
+    sha.result(hash);
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+fn be_u32(b: &[u8]) -> u32 {
+    (b[0] as u32 << 24) | (b[1] as u32 << 16) | (b[2] as u32 << 8) | b[3] as u32
+}
+
+// Each leading zero byte becomes a leading zero digit; the rest is
+// converted as a big-endian number by repeated multiply-and-add.
+fn base58_encode(table: &[u8], src: &[u8]) -> ~[u8] {
+    let zeros = src.iter().take_while(|&&b| b == 0).len();
+
+    // log(256) / log(58) is just under 1.38
+    let size = (src.len() - zeros) * 138 / 100 + 1;
+    let mut digits = vec::from_elem(size, 0u8);
+    let mut length = 0;
+
+    for &b in src.slice_from(zeros).iter() {
+        let mut carry = b as uint;
+        let mut i = 0;
+        let mut k = size;
+        while (carry != 0 || i < length) && k > 0 {
+            k -= 1;
+            carry += 256 * digits[k] as uint;
+            digits[k] = (carry % 58) as u8;
+            carry /= 58;
+            i += 1;
+        }
+        length = i;
+    }
+
+    let mut start = size - length;
+    while start < size && digits[start] == 0 {
+        start += 1;
+    }
+
+    let mut dst = vec::with_capacity(zeros + size - start);
+    for _ in range(0, zeros) {
+        dst.push(table[0]);
+    }
+    for &d in digits.slice_from(start).iter() {
+        dst.push(table[d]);
+    }
+    dst
+}
+
+fn base58_decode(decode_map: &[u8], src: &[u8]) -> Result<~[u8], ~str> {
+    let zeros = src.iter().take_while(|&&c| decode_map[c] == 0).len();
+
+    // log(58) / log(256) is just under 0.733
+    let size = (src.len() - zeros) * 733 / 1000 + 1;
+    let mut bytes = vec::from_elem(size, 0u8);
+    let mut length = 0;
+
+    for i in range(zeros, src.len()) {
+        let mut carry = decode_map[src[i]] as uint;
+        if carry == 0xff {
+            return Err(fail_decode_at(i));
+        }
+        let mut j = 0;
+        let mut k = size;
+        while (carry != 0 || j < length) && k > 0 {
+            k -= 1;
+            carry += 58 * bytes[k] as uint;
+            bytes[k] = (carry % 256) as u8;
+            carry /= 256;
+            j += 1;
+        }
+        length = j;
+    }
+
+    let mut start = size - length;
+    while start < size && bytes[start] == 0 {
+        start += 1;
+    }
+
+    let mut dst = vec::from_elem(zeros, 0u8);
+    dst.push_all(bytes.slice_from(start));
+    Ok(dst)
+}
+
+fn fail_decode_at(n: uint) -> ~str {
+    format!("illegal base58 data at input byte {}", n)
+}
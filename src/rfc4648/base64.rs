@@ -1,11 +1,24 @@
 // base64.rs
 
+use std::char;
+use std::str;
 use std::vec;
 
-// TODO: doc
+use codec::{Codec, FromDecoded};
+use display::Display;
+use util::{DecodeSize, Done, Next, Fail, input_at};
+
+/// Base64 alphabets. `Bcrypt` (`./A-Za-z0-9`) and `Crypt` (`./0-9A-Za-z`)
+/// are the unpadded encodings of password hashes in modular crypt format;
+/// `Crypt` also writes each group of 3 bytes in the little-endian bit order
+/// of crypt(3). See `md5_crypt_encode` and friends for the byte order of
+/// whole crypt(3) digests.
+#[deriving(Eq, Clone)]
 pub enum Base64Type {
     Standard,
     UrlSafe,
+    Bcrypt,
+    Crypt,
 }
 
 impl Base64Type {
@@ -21,15 +34,375 @@ impl Base64Type {
     pub fn decode_result(self, src: &[u8]) -> Result<~[u8], ~str> {
         decode_result(src, self)
     }
+    /// Decodes concatenated, independently padded segments such as
+    /// `Zg==Zm8=`, resuming after each padded quantum. The plain `decode`
+    /// rejects such input with "trailing data after padding".
+    pub fn decode_concat(self, src: &[u8]) -> ~[u8] {
+        decode_concat(src, self)
+    }
+    /// Like `decode_concat`, but returns an error instead of failing on
+    /// bad input.
+    pub fn decode_concat_result(self, src: &[u8]) -> Result<~[u8], ~str> {
+        decode_concat_result(src, self)
+    }
+    /// Decodes `src` into a newly allocated vector. On failure, returns the
+    /// bytes decoded up to the last complete valid quantum along with the
+    /// reason.
+    pub fn decode_partial(self, src: &[u8]) -> Result<~[u8], (~[u8], ~str)> {
+        let codec = &self as &Codec;
+        codec.decode_partial(src)
+    }
+    /// Encodes the first `n` bytes of `buf` over itself and returns the
+    /// encoded length. `buf` must be at least `encoded_len(n)` bytes long.
+    pub fn encode_in_place(self, buf: &mut [u8], n: uint) -> uint {
+        let codec = &self as &Codec;
+        codec.encode_in_place(buf, n)
+    }
+    /// Decodes the encoded text in `buf` over itself and returns the decoded
+    /// length. On failure, returns the number of bytes decoded up to the
+    /// last complete valid quantum along with the reason, and `buf` starts
+    /// with those bytes.
+    pub fn decode_in_place(self, buf: &mut [u8]) -> Result<uint, (uint, ~str)> {
+        let codec = &self as &Codec;
+        codec.decode_in_place(buf)
+    }
+    /// Checks `src` without producing any output and returns its decoded
+    /// length.
+    pub fn validate(self, src: &[u8]) -> Result<uint, ~str> {
+        let codec = &self as &Codec;
+        codec.validate(src)
+    }
+    /// Returns true if `src` is well-formed.
+    pub fn is_valid(self, src: &[u8]) -> bool {
+        let codec = &self as &Codec;
+        codec.is_valid(src)
+    }
+    /// Encodes `src` into a newly allocated string.
+    pub fn encode_to_string(self, src: &[u8]) -> ~str {
+        let codec = &self as &Codec;
+        codec.encode_to_string(src)
+    }
+    /// Decodes the string `src`, failing on bad input.
+    pub fn decode_str(self, src: &str) -> ~[u8] {
+        let codec = &self as &Codec;
+        codec.decode_str(src)
+    }
+    /// Decodes the string `src` into a newly allocated vector.
+    pub fn decode_str_result(self, src: &str) -> Result<~[u8], ~str> {
+        let codec = &self as &Codec;
+        codec.decode_str_result(src)
+    }
+    /// Formats `src` as base64 without allocating, e.g.
+    /// `format!("{}", base64::UrlSafe.display(token).truncate(8))`.
+    pub fn display<'a>(self, src: &'a [u8]) -> Display<'a, Base64Type> {
+        Display::new(self, src)
+    }
+}
+
+impl Codec for Base64Type {
+    fn encoded_len(&self, n: uint) -> uint {
+        match *self {
+            Bcrypt | Crypt => (n * 4 + 2) / 3,
+            _ => (n + 2) / 3 * 4
+        }
+    }
+    fn block_len(&self) -> (uint, uint) {
+        (3, 4)
+    }
+    fn decoded_len(&self, n: uint) -> uint {
+        match *self {
+            Bcrypt | Crypt => n * 3 / 4,
+            _ => n / 4 * 3
+        }
+    }
+    fn encode_to(&self, dst: &mut [u8], src: &[u8]) {
+        encode_to(dst, src, *self)
+    }
+    fn decode_partial_to(&self, dst: &mut [u8], src: &[u8]) -> Result<uint, (uint, ~str)> {
+        decode_partial_to(dst, src, *self)
+    }
+    fn validate(&self, src: &[u8]) -> Result<uint, ~str> {
+        match decode_size(&mut [], Some(src), *self, false, true) {
+            Done(n) | Next(n) => Ok(n),
+            Fail(_, reason) => Err(reason)
+        }
+    }
+    fn decode_in_place(&self, buf: &mut [u8]) -> Result<uint, (uint, ~str)> {
+        match decode_size(buf, None, *self, false, false) {
+            Done(n) | Next(n) => Ok(n),
+            Fail(n, reason) => Err((n, reason))
+        }
+    }
+}
+
+fn decode_concat(src: &[u8], base64_type: Base64Type) -> ~[u8] {
+    match decode_concat_result(src, base64_type) {
+        Ok(dst) => dst,
+        Err(reason) => fail!(reason)
+    }
+}
+
+fn decode_concat_result(src: &[u8], base64_type: Base64Type) -> Result<~[u8], ~str> {
+    let dst_length = base64_type.decoded_len(src.len());
+    let mut dst = vec::with_capacity(dst_length);
+
+    unsafe {
+        vec::raw::set_len(&mut dst, dst_length);
+    }
+
+    match decode_size(dst, Some(src), base64_type, true, false) {
+        Done(n) => unsafe { vec::raw::set_len(&mut dst, n) },
+        Next(n) => unsafe { vec::raw::set_len(&mut dst, n) },
+        Fail(_, reason) => return Err(reason)
+    }
+
+    Ok(dst)
+}
+
+fn decode_size(dst: &mut [u8], src: Option<&[u8]>, base64_type: Base64Type,
+               concat: bool, validate_only: bool) -> DecodeSize {
+    match base64_type {
+        Standard => base64_decode(BASE64_STANDARD_DECODE_MAP, dst, src, true, concat, validate_only),
+        UrlSafe => base64_decode(BASE64_URLSAFE_DECODE_MAP, dst, src, true, concat, validate_only),
+        Bcrypt => base64_decode(BASE64_BCRYPT_DECODE_MAP, dst, src, false, false, validate_only),
+        Crypt => base64_decode_le(BASE64_CRYPT_DECODE_MAP, dst, src, validate_only)
+    }
+}
+
+/// Encodes a 16-byte MD5-crypt digest into the 22 characters after the
+/// salt in `$1$salt$hash`.
+pub fn md5_crypt_encode(digest: &[u8]) -> ~[u8] {
+    crypt_encode(digest, MD5_CRYPT_ORDER)
+}
+
+/// Decodes the 22-character hash of `$1$salt$hash` into the MD5 digest.
+pub fn md5_crypt_decode(src: &[u8]) -> Result<~[u8], ~str> {
+    crypt_decode(src, MD5_CRYPT_ORDER)
+}
+
+/// Encodes a 32-byte SHA-256-crypt digest into the 43 characters after the
+/// salt in `$5$salt$hash`.
+pub fn sha256_crypt_encode(digest: &[u8]) -> ~[u8] {
+    crypt_encode(digest, SHA256_CRYPT_ORDER)
+}
+
+/// Decodes the 43-character hash of `$5$salt$hash` into the SHA-256 digest.
+pub fn sha256_crypt_decode(src: &[u8]) -> Result<~[u8], ~str> {
+    crypt_decode(src, SHA256_CRYPT_ORDER)
+}
+
+/// Encodes a 64-byte SHA-512-crypt digest into the 86 characters after the
+/// salt in `$6$salt$hash`.
+pub fn sha512_crypt_encode(digest: &[u8]) -> ~[u8] {
+    crypt_encode(digest, SHA512_CRYPT_ORDER)
+}
+
+/// Decodes the 86-character hash of `$6$salt$hash` into the SHA-512 digest.
+pub fn sha512_crypt_decode(src: &[u8]) -> Result<~[u8], ~str> {
+    crypt_decode(src, SHA512_CRYPT_ORDER)
+}
+
+/// Encodes a mailbox name in the modified UTF-7 of IMAP (RFC 3501): runs of
+/// characters outside printable ASCII become UTF-16 in unpadded base64 with
+/// `,` for `/`, between `&` and `-`, and `&` itself becomes `&-`.
+pub fn imap_utf7_encode(src: &str) -> ~str {
+    let mut dst = ~"";
+    let mut units = ~[];
+
+    for c in src.chars() {
+        if c >= ' ' && c <= '~' {
+            imap_utf7_flush(&mut dst, &mut units);
+            if c == '&' {
+                dst.push_str("&-");
+            } else {
+                dst.push_char(c);
+            }
+            continue;
+        }
+
+        // UTF-16, big-endian, with surrogate pairs above the BMP
+        let n = c as u32;
+        if n < 0x10000 {
+            units.push_all([(n >> 8) as u8, n as u8]);
+        } else {
+            let hi = 0xd800 + ((n - 0x10000) >> 10);
+            let lo = 0xdc00 + ((n - 0x10000) & 0x3ff);
+            units.push_all([(hi >> 8) as u8, hi as u8, (lo >> 8) as u8, lo as u8]);
+        }
+    }
+    imap_utf7_flush(&mut dst, &mut units);
+
+    dst
+}
+
+/// Decodes a mailbox name from IMAP modified UTF-7. Only the form produced
+/// by `imap_utf7_encode` is accepted: shifts may not hold printable ASCII,
+/// follow one another directly, or end with non-zero padding bits.
+pub fn imap_utf7_decode(src: &str) -> Result<~str, ~str> {
+    let src = src.as_bytes();
+    let mut dst = ~"";
+    let mut i = 0;
+    let mut shift_end = None;
+
+    while i < src.len() {
+        let c = src[i];
+        if c < 0x20 || c > 0x7e {
+            return Err(format!("illegal imap utf-7 data at input byte {}", i));
+        }
+        if c != AMPERSAND {
+            dst.push_char(c as char);
+            i += 1;
+            continue;
+        }
+
+        let end = match src.slice_from(i + 1).position_elem(&HYPHEN) {
+            Some(k) => i + 1 + k,
+            None => return Err(format!("unterminated imap utf-7 shift at input byte {}", i))
+        };
+        if end == i + 1 {
+            dst.push_char('&');
+        } else {
+            if shift_end == Some(i) {
+                return Err(format!("adjacent imap utf-7 shifts at input byte {}", i));
+            }
+            shift_end = Some(end + 1);
+            match imap_utf7_decode_shifted(src.slice(i + 1, end)) {
+                Ok(s) => dst.push_str(s),
+                Err(reason) => return Err(format!("{} in shift at input byte {}", reason, i))
+            }
+        }
+        i = end + 1;
+    }
+
+    Ok(dst)
+}
+
+fn imap_utf7_flush(dst: &mut ~str, units: &mut ~[u8]) {
+    if units.len() == 0 {
+        return;
+    }
+    let mut encoded = vec::from_elem((units.len() * 4 + 2) / 3, 0u8);
+    base64_encode(IMAP_UTF7_TABLE, encoded, units.as_slice(), false);
+    dst.push_char('&');
+    dst.push_str(str::from_utf8_slice(encoded));
+    dst.push_char('-');
+    units.clear();
+}
+
+fn imap_utf7_decode_shifted(src: &[u8]) -> Result<~str, ~str> {
+    let mut bytes = vec::from_elem(src.len() * 3 / 4, 0u8);
+    let n = match base64_decode(IMAP_UTF7_DECODE_MAP, bytes, Some(src), false, false, false) {
+        Done(n) | Next(n) => n,
+        Fail(_, reason) => return Err(reason)
+    };
+    if n % 2 != 0 {
+        return Err(~"odd number of utf-16 bytes");
+    }
+    let unused = match src.len() % 4 {
+        2 => 0x0f,
+        3 => 0x03,
+        _ => 0
+    };
+    if IMAP_UTF7_DECODE_MAP[src[src.len() - 1]] & unused != 0 {
+        return Err(~"non-zero padding bits");
+    }
+
+    let mut dst = ~"";
+    let mut k = 0;
+    while k < n {
+        let hi = bytes[k] as u32 << 8 | bytes[k+1] as u32;
+        k += 2;
+        let code = if hi < 0xd800 || hi > 0xdfff {
+            hi
+        } else if hi <= 0xdbff && k + 2 <= n {
+            let lo = bytes[k] as u32 << 8 | bytes[k+1] as u32;
+            if lo < 0xdc00 || lo > 0xdfff {
+                return Err(~"unpaired utf-16 surrogate");
+            }
+            k += 2;
+            0x10000 + ((hi - 0xd800) << 10) + (lo - 0xdc00)
+        } else {
+            return Err(~"unpaired utf-16 surrogate");
+        };
+        if code >= 0x20 && code <= 0x7e {
+            return Err(~"printable ascii");
+        }
+        match char::from_u32(code) {
+            Some(c) => dst.push_char(c),
+            None => return Err(~"invalid utf-16 data")
+        }
+    }
+
+    Ok(dst)
+}
+
+fn crypt_encode(digest: &[u8], order: &[u8]) -> ~[u8] {
+    assert_eq!(digest.len(), order.len());
+    let permuted: ~[u8] = order.iter().map(|&k| digest[k]).collect();
+    encode(permuted, Crypt)
+}
+
+fn crypt_decode(src: &[u8], order: &[u8]) -> Result<~[u8], ~str> {
+    let permuted = match decode_result(src, Crypt) {
+        Ok(permuted) => permuted,
+        Err(reason) => return Err(reason)
+    };
+    if permuted.len() != order.len() {
+        return Err(format!("expected {} decoded bytes, got {}", order.len(), permuted.len()));
+    }
+
+    let mut digest = vec::from_elem(order.len(), 0u8);
+    for (i, &k) in order.iter().enumerate() {
+        digest[k] = permuted[i];
+    }
+    Ok(digest)
+}
+
+/// Conversion of raw bytes to base64 text, e.g. `data.to_base64(base64::UrlSafe)`.
+pub trait ToBase64 {
+    fn to_base64(&self, base64_type: Base64Type) -> ~str;
+}
+
+/// Conversion of base64 text to raw bytes, e.g. `text.from_base64(base64::Standard)`.
+pub trait FromBase64 {
+    fn from_base64(&self, base64_type: Base64Type) -> Result<~[u8], ~str>;
+
+    /// Decodes straight into a type such as a key or token.
+    fn from_base64_into<T: FromDecoded>(&self, base64_type: Base64Type) -> Result<T, ~str> {
+        match self.from_base64(base64_type) {
+            Ok(bytes) => FromDecoded::from_decoded(bytes),
+            Err(reason) => Err(reason)
+        }
+    }
+}
+
+impl<'a> ToBase64 for &'a [u8] {
+    fn to_base64(&self, base64_type: Base64Type) -> ~str {
+        base64_type.encode_to_string(*self)
+    }
+}
+
+impl ToBase64 for ~[u8] {
+    fn to_base64(&self, base64_type: Base64Type) -> ~str {
+        base64_type.encode_to_string(self.as_slice())
+    }
 }
 
-enum DecodeSize {
-    Done(uint),       // on uncontinuable state
-    Next(uint),       // on continuable state
-    Fail(uint, ~str), // on decode failure
+impl<'a> FromBase64 for &'a str {
+    fn from_base64(&self, base64_type: Base64Type) -> Result<~[u8], ~str> {
+        decode_result(self.as_bytes(), base64_type)
+    }
+}
+
+impl FromBase64 for ~str {
+    fn from_base64(&self, base64_type: Base64Type) -> Result<~[u8], ~str> {
+        decode_result(self.as_bytes(), base64_type)
+    }
 }
 
 static PAD: u8 = 61; // '='
+static AMPERSAND: u8 = 38; // '&'
+static HYPHEN: u8 = 45; // '-'
 
 static BASE64_STANDARD_TABLE: &'static [u8] =
     bytes!("ABCDEFGHIJKLMNOPQRSTUVWXYZ",
@@ -41,6 +414,40 @@ static BASE64_URLSAFE_TABLE: &'static [u8] =
            "abcdefghijklmnopqrstuvwxyz",
            "0123456789-_");
 
+static BASE64_BCRYPT_TABLE: &'static [u8] =
+    bytes!("./",
+           "ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+           "abcdefghijklmnopqrstuvwxyz",
+           "0123456789");
+
+static BASE64_CRYPT_TABLE: &'static [u8] =
+    bytes!("./",
+           "0123456789",
+           "ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+           "abcdefghijklmnopqrstuvwxyz");
+
+// The digest bytes in the order crypt(3) encodes them, least significant
+// byte of each group first.
+static MD5_CRYPT_ORDER: [u8, ..16] = [
+    12,  6,  0, 13,  7,  1, 14,  8,  2, 15,  9,  3,
+     5, 10,  4, 11,
+];
+
+static SHA256_CRYPT_ORDER: [u8, ..32] = [
+    20, 10,  0, 11,  1, 21,  2, 22, 12, 23, 13,  3,
+    14,  4, 24,  5, 25, 15, 26, 16,  6, 17,  7, 27,
+     8, 28, 18, 29, 19,  9, 30, 31,
+];
+
+static SHA512_CRYPT_ORDER: [u8, ..64] = [
+    42, 21,  0,  1, 43, 22, 23,  2, 44, 45, 24,  3,
+     4, 46, 25, 26,  5, 47, 48, 27,  6,  7, 49, 28,
+    29,  8, 50, 51, 30,  9, 10, 52, 31, 32, 11, 53,
+    54, 33, 12, 13, 55, 34, 35, 14, 56, 57, 36, 15,
+    16, 58, 37, 38, 17, 59, 60, 39, 18, 19, 61, 40,
+    41, 20, 62, 63,
+];
+
 static BASE64_STANDARD_DECODE_MAP: [u8, ..256] = [
     255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
     255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
@@ -79,18 +486,77 @@ static BASE64_URLSAFE_DECODE_MAP: [u8, ..256] = [
     255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
 ];
 
+static BASE64_BCRYPT_DECODE_MAP: [u8, ..256] = [
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,   0,   1,
+     54,  55,  56,  57,  58,  59,  60,  61,  62,  63, 255, 255, 255, 255, 255, 255,
+    255,   2,   3,   4,   5,   6,   7,   8,   9,  10,  11,  12,  13,  14,  15,  16,
+     17,  18,  19,  20,  21,  22,  23,  24,  25,  26,  27, 255, 255, 255, 255, 255,
+    255,  28,  29,  30,  31,  32,  33,  34,  35,  36,  37,  38,  39,  40,  41,  42,
+     43,  44,  45,  46,  47,  48,  49,  50,  51,  52,  53, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+];
+
+static BASE64_CRYPT_DECODE_MAP: [u8, ..256] = [
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,   0,   1,
+      2,   3,   4,   5,   6,   7,   8,   9,  10,  11, 255, 255, 255, 255, 255, 255,
+    255,  12,  13,  14,  15,  16,  17,  18,  19,  20,  21,  22,  23,  24,  25,  26,
+     27,  28,  29,  30,  31,  32,  33,  34,  35,  36,  37, 255, 255, 255, 255, 255,
+    255,  38,  39,  40,  41,  42,  43,  44,  45,  46,  47,  48,  49,  50,  51,  52,
+     53,  54,  55,  56,  57,  58,  59,  60,  61,  62,  63, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+];
+
+static IMAP_UTF7_TABLE: &'static [u8] =
+    bytes!("ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+           "abcdefghijklmnopqrstuvwxyz",
+           "0123456789+,");
+
+static IMAP_UTF7_DECODE_MAP: [u8, ..256] = [
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,  62,  63, 255, 255, 255,
+     52,  53,  54,  55,  56,  57,  58,  59,  60,  61, 255, 255, 255, 255, 255, 255,
+    255,   0,   1,   2,   3,   4,   5,   6,   7,   8,   9,  10,  11,  12,  13,  14,
+     15,  16,  17,  18,  19,  20,  21,  22,  23,  24,  25, 255, 255, 255, 255, 255,
+    255,  26,  27,  28,  29,  30,  31,  32,  33,  34,  35,  36,  37,  38,  39,  40,
+     41,  42,  43,  44,  45,  46,  47,  48,  49,  50,  51, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+];
+
 fn encode(src: &[u8], base64_type: Base64Type) -> ~[u8] {
-    let dst_length = (src.len() + 2) / 3 * 4;
+    let dst_length = base64_type.encoded_len(src.len());
     let mut dst = vec::with_capacity(dst_length);
 
     unsafe {
         vec::raw::set_len(&mut dst, dst_length);
     }
 
-    match base64_type {
-        Standard => base64_encode(BASE64_STANDARD_TABLE, dst, src),
-        UrlSafe => base64_encode(BASE64_URLSAFE_TABLE, dst, src)
-    }
+    encode_to(dst, src, base64_type);
 
     dst
 }
@@ -103,19 +569,14 @@ fn decode(src: &[u8], base64_type: Base64Type) -> ~[u8] {
 }
 
 fn decode_result(src: &[u8], base64_type: Base64Type) -> Result<~[u8], ~str> {
-    let dst_length = src.len() / 4 * 3;
+    let dst_length = base64_type.decoded_len(src.len());
     let mut dst = vec::with_capacity(dst_length);
 
     unsafe {
         vec::raw::set_len(&mut dst, dst_length);
     }
 
-    let size = match base64_type {
-        Standard => base64_decode(BASE64_STANDARD_DECODE_MAP, dst, src),
-        UrlSafe => base64_decode(BASE64_URLSAFE_DECODE_MAP, dst, src)
-    };
-
-    match size {
+    match decode_size(dst, Some(src), base64_type, false, false) {
         Done(n) => unsafe { vec::raw::set_len(&mut dst, n) },
         Next(n) => unsafe { vec::raw::set_len(&mut dst, n) },
         Fail(_, reason) => return Err(reason)
@@ -124,7 +585,26 @@ fn decode_result(src: &[u8], base64_type: Base64Type) -> Result<~[u8], ~str> {
     Ok(dst)
 }
 
-fn base64_encode(table: &[u8], dst: &mut [u8], src: &[u8]) {
+fn encode_to(dst: &mut [u8], src: &[u8], base64_type: Base64Type) {
+    match base64_type {
+        Standard => base64_encode(BASE64_STANDARD_TABLE, dst, src, true),
+        UrlSafe => base64_encode(BASE64_URLSAFE_TABLE, dst, src, true),
+        Bcrypt => base64_encode(BASE64_BCRYPT_TABLE, dst, src, false),
+        Crypt => base64_encode_le(BASE64_CRYPT_TABLE, dst, src)
+    }
+}
+
+fn decode_partial_to(dst: &mut [u8], src: &[u8], base64_type: Base64Type) -> Result<uint, (uint, ~str)> {
+    match decode_size(dst, Some(src), base64_type, false, false) {
+        Done(n) => Ok(n),
+        Next(n) => Ok(n),
+        Fail(n, reason) => Err((n, reason))
+    }
+}
+
+// Without `padded`, the final quantum is cut down to the characters that
+// carry data.
+fn base64_encode(table: &[u8], dst: &mut [u8], src: &[u8], padded: bool) {
     let len = src.len();
     let pad = len % 3;
     let mut i = 0;
@@ -147,25 +627,60 @@ fn base64_encode(table: &[u8], dst: &mut [u8], src: &[u8]) {
         let n = (src[i] as u32)<<16;
         dst[0] = table[n>>18 & 0x3f];
         dst[1] = table[n>>12 & 0x3f];
-        dst[2] = PAD;
-        dst[3] = PAD;
+        if padded {
+            dst[2] = PAD;
+            dst[3] = PAD;
+        }
     } else if pad == 2 {
         let n = (src[i] as u32)<<16 | (src[i+1] as u32)<<8;
         dst[0] = table[n>>18 & 0x3f];
         dst[1] = table[n>>12 & 0x3f];
         dst[2] = table[n>>6  & 0x3f];
-        dst[3] = PAD;
+        if padded {
+            dst[3] = PAD;
+        }
     }
 }
 
-fn base64_decode(decode_map: &[u8], dst: &mut [u8], src: &[u8]) -> DecodeSize {
-    let len = src.len();
+// Like `base64_encode` without padding, but in the bit order of crypt(3):
+// each group of 3 bytes is a little-endian number, written from its least
+// significant 6 bits up.
+fn base64_encode_le(table: &[u8], dst: &mut [u8], src: &[u8]) {
+    for (g, group) in src.chunk_iter(3).enumerate() {
+        let mut n = 0u32;
+        for (k, &b) in group.iter().enumerate() {
+            n |= (b as u32) << (8 * k);
+        }
+        for k in range(0, group.len() + 1) {
+            dst[g*4 + k] = table[n >> (6 * k) & 0x3f];
+        }
+    }
+}
+
+// Without `padded`, the final quantum may be short and `=` is not accepted.
+// With `concat`, decoding resumes after a padded quantum instead of failing
+// on trailing data.
+fn base64_decode(decode_map: &[u8], dst: &mut [u8], src: Option<&[u8]>,
+                 padded: bool, concat: bool, validate_only: bool) -> DecodeSize {
+    let len = match src { Some(src) => src.len(), None => dst.len() };
 
     if len == 0 {
         return Done(0);
     }
-    if len < 4 || (len % 4) != 0 {
-        return Fail(0, ~"the input length should be divisible by 4");
+    if padded && (len < 4 || (len % 4) != 0) {
+        // decode the complete quanta anyway so that callers can recover them,
+        // but report the length as the reason, as it is checked first
+        let head = len / 4 * 4;
+        let result = match src {
+            Some(src) => base64_decode(decode_map, dst, Some(src.slice_to(head)),
+                                       padded, concat, validate_only),
+            None => base64_decode(decode_map, dst.mut_slice_to(head), None,
+                                  padded, concat, validate_only)
+        };
+        let ndecoded = match result {
+            Done(n) | Next(n) | Fail(n, _) => n
+        };
+        return Fail(ndecoded, ~"the input length should be divisible by 4");
     }
 
     let mut end = false;
@@ -173,23 +688,37 @@ fn base64_decode(decode_map: &[u8], dst: &mut [u8], src: &[u8]) -> DecodeSize {
     let mut ndecoded = 0;
     let mut i = 0;
 
-    while leftover > 0 && !end {
+    while leftover > 0 {
+        if end {
+            if !concat {
+                return Fail(ndecoded, fail_trailing_at(len - leftover));
+            }
+            end = false;
+        }
+
         let mut buf = [0xff, ..4];
         let mut buf_len = 4;
 
         let mut j = 0u;
         while j < 4 {
             if leftover == 0 {
-                return Fail(ndecoded, fail_decode_at(len - leftover - j));
+                if padded || j == 1 {
+                    return Fail(ndecoded, fail_decode_at(len - leftover - j));
+                }
+                buf_len = j;
+                break;
             }
-            let c = src[len - leftover];
+            let c = input_at(dst, src, len - leftover);
             leftover -= 1;
-            if c == PAD && j >= 2 && leftover < 4 {
+            if padded && c == PAD && j >= 2 {
                 if leftover + j < 4 - 1 {
                     return Fail(ndecoded, fail_decode_at(len));
                 }
-                if leftover > 0 && src[len - leftover] != PAD {
-                    return Fail(ndecoded, fail_decode_at(len - leftover - 1));
+                if j == 2 {
+                    if input_at(dst, src, len - leftover) != PAD {
+                        return Fail(ndecoded, fail_decode_at(len - leftover - 1));
+                    }
+                    leftover -= 1;
                 }
                 buf_len = j;
                 end = true;
@@ -202,17 +731,69 @@ fn base64_decode(decode_map: &[u8], dst: &mut [u8], src: &[u8]) -> DecodeSize {
             j += 1;
         }
 
-        dst[i] = buf[0]<<2 | buf[1]>>4;
-        dst[i+1] = if buf_len > 2 { buf[1]<<4 | buf[2]>>2 } else { 0 };
-        dst[i+2] = if buf_len > 3 { buf[2]<<6 | buf[3]    } else { 0 };
+        if !validate_only {
+            if i + buf_len - 1 > dst.len() {
+                return Fail(ndecoded, fail_short_dst(dst.len()));
+            }
+
+            // a short final quantum may have no room in `dst` for 3 bytes
+            dst[i] = buf[0]<<2 | buf[1]>>4;
+            if buf_len > 2 { dst[i+1] = buf[1]<<4 | buf[2]>>2; }
+            if buf_len > 3 { dst[i+2] = buf[2]<<6 | buf[3]; }
+        }
 
-        i += 3;
         ndecoded += buf_len - 1;
+        i = ndecoded;
     }
 
     if end { Done(ndecoded) } else { Next(ndecoded) }
 }
 
+// The inverse of `base64_encode_le`.
+fn base64_decode_le(decode_map: &[u8], dst: &mut [u8], src: Option<&[u8]>,
+                    validate_only: bool) -> DecodeSize {
+    let len = match src { Some(src) => src.len(), None => dst.len() };
+    let mut ndecoded = 0;
+    let mut i = 0;
+
+    while i < len {
+        let group_len = if len - i >= 4 { 4 } else { len - i };
+        if group_len == 1 {
+            return Fail(ndecoded, fail_decode_at(i));
+        }
+        let mut n = 0u32;
+        for k in range(0, group_len) {
+            let v = decode_map[input_at(dst, src, i + k)];
+            if v == 0xff {
+                return Fail(ndecoded, fail_decode_at(i + k));
+            }
+            n |= (v as u32) << (6 * k);
+        }
+        i += group_len;
+
+        let m = group_len - 1;
+        if !validate_only {
+            if ndecoded + m > dst.len() {
+                return Fail(ndecoded, fail_short_dst(dst.len()));
+            }
+            for k in range(0, m) {
+                dst[ndecoded + k] = (n >> (8 * k)) as u8;
+            }
+        }
+        ndecoded += m;
+    }
+
+    Done(ndecoded)
+}
+
+fn fail_short_dst(n: uint) -> ~str {
+    format!("output buffer of {} bytes is too small", n)
+}
+
 fn fail_decode_at(n: uint) -> ~str {
     format!("illegal base64 data at input byte {}", n)
 }
+
+fn fail_trailing_at(n: uint) -> ~str {
+    format!("trailing data after padding at input byte {}", n)
+}
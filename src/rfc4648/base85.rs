@@ -0,0 +1,370 @@
+// base85.rs
+
+use std::vec;
+
+use util;
+
+/// Base85 flavors.
+///
+/// `Ascii85` is the Adobe variant used in PostScript and PDF: its encoded
+/// form is wrapped in `<~` and `~>`, an all-zero group is written as `z`
+/// and whitespace is ignored when decoding. `Z85` is the ZeroMQ variant
+/// (RFC 32), which only encodes multiples of 4 bytes. `Ascii85` writes a
+/// final group of `n` bytes as `n + 1` characters.
+///
+/// `Git` is the line format of git binary patches. Each line holds up to 52
+/// bytes as whole 5-character groups, the last one padded with zeros, after
+/// a character giving the byte count: `A` to `Z` for 1 to 26 and `a` to `z`
+/// for 27 to 52. Every line ends with `\n`, and blank lines such as the one
+/// closing a hunk are skipped when decoding.
+#[deriving(Eq, Clone)]
+pub enum Base85Type {
+    Ascii85,
+    Z85,
+    Git,
+}
+
+impl Base85Type {
+    /// Encodes `src`. Fails for `Z85` if its length is not a multiple of 4;
+    /// use `encode_result` to get an error instead.
+    pub fn encode(self, src: &[u8]) -> ~[u8] {
+        match encode_result(src, self) {
+            Ok(dst) => dst,
+            Err(reason) => fail!(reason)
+        }
+    }
+    /// Encodes `src`, or returns an error for `Z85` if its length is not a
+    /// multiple of 4. The other flavors encode any input.
+    pub fn encode_result(self, src: &[u8]) -> Result<~[u8], ~str> {
+        encode_result(src, self)
+    }
+    /// Decodes `src` into a newly allocated vector, failing on bad input.
+    /// `Ascii85` requires the `<~` and `~>` delimiters, except that `<~`
+    /// may be left out.
+    pub fn decode(self, src: &[u8]) -> ~[u8] {
+        decode(src, self)
+    }
+    /// Decodes `src` into a newly allocated vector.
+    pub fn decode_result(self, src: &[u8]) -> Result<~[u8], ~str> {
+        decode_result(src, self)
+    }
+    /// Like `encode`, but returns a string. Fails for `Z85` in the same
+    /// way.
+    pub fn encode_to_string(self, src: &[u8]) -> ~str {
+        encode_to_string(src, self)
+    }
+    /// Decodes the string `src` into a newly allocated vector.
+    pub fn decode_str_result(self, src: &str) -> Result<~[u8], ~str> {
+        decode_result(src.as_bytes(), self)
+    }
+}
+
+static ZERO_GROUP: u8 = 122; // 'z'
+static NEWLINE: u8 = 10; // '\n'
+
+static GIT_LINE_LEN: uint = 52;
+
+static ASCII85_TABLE: &'static [u8] =
+    bytes!("!\"#$%&'()*+,-./0123456789:;<=>?@",
+           "ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`",
+           "abcdefghijklmnopqrstu");
+
+static Z85_TABLE: &'static [u8] =
+    bytes!("0123456789",
+           "abcdefghijklmnopqrstuvwxyz",
+           "ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+           ".-:+=^!/*?&<>()[]{}@%$#");
+
+static GIT_TABLE: &'static [u8] =
+    bytes!("0123456789",
+           "ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+           "abcdefghijklmnopqrstuvwxyz",
+           "!#$%&()*+-;<=>?@^_`{|}~");
+
+static ASCII85_DECODE_MAP: [u8, ..256] = [
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255,   0,   1,   2,   3,   4,   5,   6,   7,   8,   9,  10,  11,  12,  13,  14,
+     15,  16,  17,  18,  19,  20,  21,  22,  23,  24,  25,  26,  27,  28,  29,  30,
+     31,  32,  33,  34,  35,  36,  37,  38,  39,  40,  41,  42,  43,  44,  45,  46,
+     47,  48,  49,  50,  51,  52,  53,  54,  55,  56,  57,  58,  59,  60,  61,  62,
+     63,  64,  65,  66,  67,  68,  69,  70,  71,  72,  73,  74,  75,  76,  77,  78,
+     79,  80,  81,  82,  83,  84, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+];
+
+static Z85_DECODE_MAP: [u8, ..256] = [
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255,  68, 255,  84,  83,  82,  72, 255,  75,  76,  70,  65, 255,  63,  62,  69,
+      0,   1,   2,   3,   4,   5,   6,   7,   8,   9,  64, 255,  73,  66,  74,  71,
+     81,  36,  37,  38,  39,  40,  41,  42,  43,  44,  45,  46,  47,  48,  49,  50,
+     51,  52,  53,  54,  55,  56,  57,  58,  59,  60,  61,  77, 255,  78,  67, 255,
+    255,  10,  11,  12,  13,  14,  15,  16,  17,  18,  19,  20,  21,  22,  23,  24,
+     25,  26,  27,  28,  29,  30,  31,  32,  33,  34,  35,  79, 255,  80, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+];
+
+static GIT_DECODE_MAP: [u8, ..256] = [
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255,  62, 255,  63,  64,  65,  66, 255,  67,  68,  69,  70, 255,  71, 255, 255,
+      0,   1,   2,   3,   4,   5,   6,   7,   8,   9, 255,  72,  73,  74,  75,  76,
+     77,  10,  11,  12,  13,  14,  15,  16,  17,  18,  19,  20,  21,  22,  23,  24,
+     25,  26,  27,  28,  29,  30,  31,  32,  33,  34,  35, 255, 255, 255,  78,  79,
+     80,  36,  37,  38,  39,  40,  41,  42,  43,  44,  45,  46,  47,  48,  49,  50,
+     51,  52,  53,  54,  55,  56,  57,  58,  59,  60,  61,  81,  82,  83,  84, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+];
+
+fn encode_result(src: &[u8], base85_type: Base85Type) -> Result<~[u8], ~str> {
+    match base85_type {
+        Ascii85 => {
+            let mut dst = bytes!("<~").to_owned();
+            dst.push_all(base85_encode(ASCII85_TABLE, src, true));
+            dst.push_all(bytes!("~>"));
+            Ok(dst)
+        }
+        Z85 => {
+            if src.len() % 4 != 0 {
+                return Err(format!("z85 data length {} is not a multiple of 4", src.len()));
+            }
+            Ok(base85_encode(Z85_TABLE, src, false))
+        }
+        Git => Ok(git_encode(src))
+    }
+}
+
+fn encode_to_string(src: &[u8], base85_type: Base85Type) -> ~str {
+    util::encoded_to_string(base85_type.encode(src))
+}
+
+fn decode(src: &[u8], base85_type: Base85Type) -> ~[u8] {
+    match decode_result(src, base85_type) {
+        Ok(dst) => dst,
+        Err(reason) => fail!(reason)
+    }
+}
+
+fn decode_result(src: &[u8], base85_type: Base85Type) -> Result<~[u8], ~str> {
+    match base85_type {
+        Ascii85 => {
+            let (start, end) = match ascii85_bounds(src) {
+                Ok(bounds) => bounds,
+                Err(reason) => return Err(reason)
+            };
+            base85_decode(ASCII85_DECODE_MAP, src.slice(start, end), start, true)
+        }
+        Z85 => {
+            if src.len() % 5 != 0 {
+                return Err(format!("z85 data length {} is not a multiple of 5", src.len()));
+            }
+            base85_decode(Z85_DECODE_MAP, src, 0, false)
+        }
+        Git => git_decode(src)
+    }
+}
+
+// Finds the data between the optional `<~` and the `~>` delimiters, which
+// may be surrounded by whitespace. `~>` is required after `<~`.
+fn ascii85_bounds(src: &[u8]) -> Result<(uint, uint), ~str> {
+    let mut start = 0;
+    while start < src.len() && is_space(src[start]) {
+        start += 1;
+    }
+    let opened = src.slice_from(start).starts_with(bytes!("<~"));
+    if opened {
+        start += 2;
+    }
+
+    let mut end = src.len();
+    while end > start && is_space(src[end - 1]) {
+        end -= 1;
+    }
+    if src.slice(start, end).ends_with(bytes!("~>")) {
+        end -= 2;
+    } else if opened {
+        return Err(~"missing ascii85 end delimiter ~>");
+    }
+
+    Ok((start, end))
+}
+
+fn git_encode(src: &[u8]) -> ~[u8] {
+    let nlines = (src.len() + GIT_LINE_LEN - 1) / GIT_LINE_LEN;
+    let mut dst = vec::with_capacity(nlines * (GIT_LINE_LEN / 4 * 5 + 2));
+
+    for line in src.chunk_iter(GIT_LINE_LEN) {
+        let n = line.len();
+        dst.push(if n <= 26 { 64 + n as u8 } else { 70 + n as u8 }); // 'A', 'a'
+
+        // unlike the other flavors, the last group is written in full
+        let mut padded = line.to_owned();
+        padded.grow((4 - n % 4) % 4, &0u8);
+        dst.push_all(base85_encode(GIT_TABLE, padded, false));
+        dst.push(NEWLINE);
+    }
+
+    dst
+}
+
+fn git_decode(src: &[u8]) -> Result<~[u8], ~str> {
+    let mut dst = vec::with_capacity(src.len() / 5 * 4);
+    let mut start = 0;
+
+    while start < src.len() {
+        let mut end = start;
+        while end < src.len() && src[end] != NEWLINE {
+            end += 1;
+        }
+        if end > start {
+            let c = src[start];
+            let n = match c {
+                65..90  => c as uint - 64, // 'A' to 'Z'
+                97..122 => c as uint - 70, // 'a' to 'z'
+                _ => return Err(format!("illegal base85 line length {} at input byte {}",
+                                        c as char, start))
+            };
+            let expect = (n + 3) / 4 * 5;
+            if end - start - 1 != expect {
+                return Err(format!("base85 line at input byte {} should have {} characters, got {}",
+                                   start, expect + 1, end - start));
+            }
+            match base85_decode(GIT_DECODE_MAP, src.slice(start + 1, end), start + 1, false) {
+                Ok(bytes) => dst.push_all(bytes.slice_to(n)),
+                Err(reason) => return Err(reason)
+            }
+        }
+        start = end + 1;
+    }
+
+    Ok(dst)
+}
+
+fn is_space(c: u8) -> bool {
+    c == 32 || (c >= 9 && c <= 13)
+}
+
+// Each group of 4 bytes is a big-endian number written as 5 base85
+// digits, most significant first. A final group of `n` bytes is padded
+// with zeros and cut down to `n + 1` digits.
+fn base85_encode(table: &[u8], src: &[u8], zero_group: bool) -> ~[u8] {
+    let mut dst = vec::with_capacity((src.len() + 3) / 4 * 5);
+
+    for group in src.chunk_iter(4) {
+        let mut word = 0u32;
+        for i in range(0u, 4) {
+            let b = if i < group.len() { group[i] } else { 0 };
+            word = word << 8 | b as u32;
+        }
+        if zero_group && word == 0 && group.len() == 4 {
+            dst.push(ZERO_GROUP);
+            continue;
+        }
+
+        let mut digits = [0u8, ..5];
+        for i in range(0u, 5) {
+            digits[4 - i] = table[(word % 85) as uint];
+            word /= 85;
+        }
+        dst.push_all(digits.slice_to(group.len() + 1));
+    }
+
+    dst
+}
+
+// `offset` is the position of `src` in the original input, for error
+// messages. `ascii85` enables whitespace skipping and the `z` shorthand.
+fn base85_decode(decode_map: &[u8], src: &[u8], offset: uint, ascii85: bool) -> Result<~[u8], ~str> {
+    let mut dst = vec::with_capacity(src.len() / 5 * 4 + 4);
+    let mut group = [0u8, ..5];
+    let mut k = 0;
+
+    for i in range(0, src.len()) {
+        let c = src[i];
+        if ascii85 && is_space(c) {
+            continue;
+        }
+        if ascii85 && c == ZERO_GROUP {
+            if k != 0 {
+                return Err(fail_decode_at(offset + i));
+            }
+            dst.push_all([0u8, 0, 0, 0]);
+            continue;
+        }
+
+        let v = decode_map[c];
+        if v == 0xff {
+            return Err(fail_decode_at(offset + i));
+        }
+        group[k] = v;
+        k += 1;
+
+        if k == 5 {
+            match group_value(group) {
+                Some(word) => push_word(&mut dst, word, 4),
+                None => return Err(fail_overflow_at(offset + i))
+            }
+            k = 0;
+        }
+    }
+
+    if k == 1 {
+        return Err(~"truncated base85 group");
+    }
+    if k > 1 {
+        // pad with the highest digit so that truncation rounds back down
+        for j in range(k, 5) {
+            group[j] = 84;
+        }
+        match group_value(group) {
+            Some(word) => push_word(&mut dst, word, k - 1),
+            None => return Err(fail_overflow_at(offset + src.len()))
+        }
+    }
+
+    Ok(dst)
+}
+
+fn group_value(group: &[u8]) -> Option<u32> {
+    let mut n = 0u64;
+    for &d in group.iter() {
+        n = n * 85 + d as u64;
+    }
+    if n > 0xffff_ffff { None } else { Some(n as u32) }
+}
+
+fn push_word(dst: &mut ~[u8], word: u32, n: uint) {
+    for i in range(0, n) {
+        dst.push((word >> (24 - 8 * i)) as u8);
+    }
+}
+
+fn fail_decode_at(n: uint) -> ~str {
+    format!("illegal base85 data at input byte {}", n)
+}
+
+fn fail_overflow_at(n: uint) -> ~str {
+    format!("base85 group overflows 32 bits at input byte {}", n)
+}
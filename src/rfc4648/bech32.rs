@@ -0,0 +1,255 @@
+// bech32.rs
+
+use std::ascii::StrAsciiExt;
+use std::str;
+use std::to_str::ToStr;
+
+use base32;
+use util;
+
+/// Checksum variants: `Bech32` from BIP 173 and `Bech32m` from BIP 350.
+///
+/// A bech32 string is a human-readable part (HRP), the separator `1`, the
+/// data in 5-bit groups and a 6-character checksum, at most 90 characters
+/// in all and in a single case.
+#[deriving(Eq, Clone)]
+pub enum Bech32Type {
+    Bech32,
+    Bech32m,
+}
+
+impl Bech32Type {
+    /// Encodes the bytes `data` under `hrp`.
+    pub fn encode(self, hrp: &str, data: &[u8]) -> Result<~str, Bech32Error> {
+        encode_quintets(hrp, base32::to_quintets(data), self)
+    }
+    /// Decodes `src` into its HRP, in lowercase, and data bytes.
+    pub fn decode(self, src: &str) -> Result<(~str, ~[u8]), Bech32Error> {
+        let (hrp, quintets) = match decode_quintets(src, self) {
+            Ok(parts) => parts,
+            Err(err) => return Err(err)
+        };
+        match base32::from_quintets(quintets) {
+            Ok(data) => Ok((hrp, data)),
+            Err(_) => Err(InvalidPadding)
+        }
+    }
+    /// Encodes data that is already split into 5-bit groups, such as a
+    /// segwit address with its witness version.
+    pub fn encode_quintets(self, hrp: &str, quintets: &[u8]) -> Result<~str, Bech32Error> {
+        encode_quintets(hrp, quintets, self)
+    }
+    /// Decodes `src` into its HRP and data as 5-bit groups.
+    pub fn decode_quintets(self, src: &str) -> Result<(~str, ~[u8]), Bech32Error> {
+        decode_quintets(src, self)
+    }
+}
+
+/// Reasons for bech32 encoding or decoding to fail. Positions are byte
+/// offsets in the bech32 string.
+#[deriving(Eq, Clone)]
+pub enum Bech32Error {
+    /// The string would be, or is, of the given length, outside 8 to 90.
+    InvalidLength(uint),
+    /// The string mixes upper and lower case.
+    MixedCase,
+    /// There is no `1` between the HRP and the data.
+    MissingSeparator,
+    /// The HRP is empty or has a character outside `!` to `~` at the given
+    /// position.
+    InvalidHrp(uint),
+    /// The character at the given position is not allowed there.
+    InvalidChar(uint),
+    /// The checksum does not match. If changing a single character would
+    /// fix it, this is the position of that likely typo.
+    InvalidChecksum(Option<uint>),
+    /// The data does not split evenly into bytes.
+    InvalidPadding,
+}
+
+impl ToStr for Bech32Error {
+    fn to_str(&self) -> ~str {
+        match *self {
+            InvalidLength(n) => format!("invalid bech32 length {}", n),
+            MixedCase => ~"mixed case bech32 string",
+            MissingSeparator => ~"missing bech32 separator",
+            InvalidHrp(n) => format!("invalid bech32 hrp at byte {}", n),
+            InvalidChar(n) => format!("illegal bech32 data at byte {}", n),
+            InvalidChecksum(Some(n)) => format!("invalid bech32 checksum, likely typo at byte {}", n),
+            InvalidChecksum(None) => ~"invalid bech32 checksum",
+            InvalidPadding => ~"invalid bech32 padding"
+        }
+    }
+}
+
+static SEPARATOR: u8 = 49; // '1'
+static CHECKSUM_LEN: uint = 6;
+static MAX_LEN: uint = 90;
+
+static BECH32_TABLE: &'static [u8] = bytes!("qpzry9x8gf2tvdw0s3jn54khce6mua7l");
+
+static BECH32_DECODE_MAP: [u8, ..256] = [
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+     15, 255,  10,  17,  21,  20,  26,  30,   7,   5, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255,  29, 255,  24,  13,  25,   9,   8,  23, 255,  18,  22,  31,  27,  19, 255,
+      1,   0,   3,  16,  11,  28,  12,  14,   6,   4,   2, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+];
+
+static GENERATOR: [u32, ..5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+fn encode_quintets(hrp: &str, quintets: &[u8], bech32_type: Bech32Type) -> Result<~str, Bech32Error> {
+    let hrp = hrp.to_ascii_lower();
+    let len = hrp.len() + 1 + quintets.len() + CHECKSUM_LEN;
+    if len > MAX_LEN {
+        return Err(InvalidLength(len));
+    }
+    if hrp.len() == 0 {
+        return Err(InvalidHrp(0));
+    }
+    for (i, &c) in hrp.as_bytes().iter().enumerate() {
+        if c < 33 || c > 126 {
+            return Err(InvalidHrp(i));
+        }
+    }
+
+    let mut dst = hrp.as_bytes().to_owned();
+    dst.push(SEPARATOR);
+    for &q in quintets.iter() {
+        assert!(q < 32);
+        dst.push(BECH32_TABLE[q]);
+    }
+    for &q in checksum(hrp.as_bytes(), quintets, bech32_type).iter() {
+        dst.push(BECH32_TABLE[q]);
+    }
+
+    Ok(util::encoded_to_string(dst))
+}
+
+fn decode_quintets(src: &str, bech32_type: Bech32Type) -> Result<(~str, ~[u8]), Bech32Error> {
+    let src = src.as_bytes();
+    if src.len() < 2 + CHECKSUM_LEN || src.len() > MAX_LEN {
+        return Err(InvalidLength(src.len()));
+    }
+
+    let mut lower = false;
+    let mut upper = false;
+    for (i, &c) in src.iter().enumerate() {
+        if c < 33 || c > 126 {
+            return Err(InvalidChar(i));
+        }
+        lower |= c >= 97 && c <= 122;
+        upper |= c >= 65 && c <= 90;
+    }
+    if lower && upper {
+        return Err(MixedCase);
+    }
+    let src = str::from_utf8_slice(src).to_ascii_lower();
+    let src = src.as_bytes();
+
+    let sep = match src.rposition_elem(&SEPARATOR) {
+        Some(sep) => sep,
+        None => return Err(MissingSeparator)
+    };
+    if sep == 0 {
+        return Err(InvalidHrp(0));
+    }
+    if sep + 1 + CHECKSUM_LEN > src.len() {
+        return Err(InvalidLength(src.len()));
+    }
+
+    let hrp = src.slice_to(sep);
+    let mut values = ~[];
+    for i in range(sep + 1, src.len()) {
+        let v = BECH32_DECODE_MAP[src[i]];
+        if v == 0xff {
+            return Err(InvalidChar(i));
+        }
+        values.push(v);
+    }
+
+    if !verify(hrp, values, bech32_type) {
+        let typo = match locate_typo(hrp, values, bech32_type) {
+            Some(k) => Some(sep + 1 + k),
+            None => None
+        };
+        return Err(InvalidChecksum(typo));
+    }
+
+    values.truncate(values.len() - CHECKSUM_LEN);
+    Ok((str::from_utf8_slice(hrp).to_owned(), values))
+}
+
+fn checksum_constant(bech32_type: Bech32Type) -> u32 {
+    match bech32_type {
+        Bech32 => 1,
+        Bech32m => 0x2bc830a3
+    }
+}
+
+fn polymod(values: &[u8], chk: u32) -> u32 {
+    let mut chk = chk;
+    for &v in values.iter() {
+        let top = chk >> 25;
+        chk = (chk & 0x1ffffff) << 5 ^ v as u32;
+        for i in range(0u, 5) {
+            if (top >> i) & 1 == 1 {
+                chk ^= GENERATOR[i];
+            }
+        }
+    }
+    chk
+}
+
+// The checksum covers the HRP expanded to the high bits of each character,
+// a zero, and the low bits of each character, followed by the data.
+fn hrp_polymod(hrp: &[u8]) -> u32 {
+    let high: ~[u8] = hrp.iter().map(|&c| c >> 5).collect();
+    let low: ~[u8] = hrp.iter().map(|&c| c & 31).collect();
+    let chk = polymod(high, 1);
+    let chk = polymod([0], chk);
+    polymod(low, chk)
+}
+
+fn checksum(hrp: &[u8], quintets: &[u8], bech32_type: Bech32Type) -> [u8, ..6] {
+    let chk = polymod(quintets, hrp_polymod(hrp));
+    let chk = polymod([0, 0, 0, 0, 0, 0], chk) ^ checksum_constant(bech32_type);
+    let mut dst = [0u8, ..6];
+    for i in range(0u, 6) {
+        dst[i] = (chk >> 5 * (5 - i) & 31) as u8;
+    }
+    dst
+}
+
+fn verify(hrp: &[u8], values: &[u8], bech32_type: Bech32Type) -> bool {
+    polymod(values, hrp_polymod(hrp)) == checksum_constant(bech32_type)
+}
+
+// Looks for a single substituted data character that explains a checksum
+// failure. The code detects any such error, so at most one position fits.
+fn locate_typo(hrp: &[u8], values: &[u8], bech32_type: Bech32Type) -> Option<uint> {
+    let mut values = values.to_owned();
+    for k in range(0, values.len()) {
+        let orig = values[k];
+        for v in range(0u8, 32) {
+            if v == orig { continue; }
+            values[k] = v;
+            if verify(hrp, values, bech32_type) {
+                return Some(k);
+            }
+        }
+        values[k] = orig;
+    }
+    None
+}
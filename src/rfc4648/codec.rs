@@ -0,0 +1,294 @@
+// codec.rs
+
+use std::str;
+use std::cmp;
+use std::vec;
+use std::rt::io::{Reader, Writer};
+
+use util;
+
+static STREAM_BUF_LEN: uint = 4096;
+
+/// Operations shared by every encoding in this crate, so that generic code
+/// can be written once and parameterized over `base16::Standard`,
+/// `base32::Hex`, `base64::UrlSafe` and so on.
+pub trait Codec {
+    /// Returns the length of the encoded form of `n` bytes.
+    fn encoded_len(&self, n: uint) -> uint;
+
+    /// Returns the maximum length of the decoded form of `n` encoded bytes.
+    fn decoded_len(&self, n: uint) -> uint;
+
+    /// Returns the sizes of a raw quantum and of its encoded form.
+    fn block_len(&self) -> (uint, uint);
+
+    /// Encodes `src` into `dst`, which must be exactly
+    /// `encoded_len(src.len())` bytes long.
+    fn encode_to(&self, dst: &mut [u8], src: &[u8]);
+
+    /// Decodes `src` into `dst`, which must be at least
+    /// `decoded_len(src.len())` bytes long, and returns the number of
+    /// bytes written. On failure, returns the number of bytes decoded up to
+    /// the last complete valid quantum along with the reason, including
+    /// when `dst` is too small.
+    fn decode_partial_to(&self, dst: &mut [u8], src: &[u8]) -> Result<uint, (uint, ~str)>;
+
+    /// Checks `src` without producing any output and returns its decoded
+    /// length.
+    fn validate(&self, src: &[u8]) -> Result<uint, ~str>;
+
+    /// Decodes the encoded text in `buf` over itself and returns the decoded
+    /// length. On failure, returns the number of bytes decoded up to the
+    /// last complete valid quantum along with the reason, and `buf` starts
+    /// with those bytes.
+    fn decode_in_place(&self, buf: &mut [u8]) -> Result<uint, (uint, ~str)>;
+
+    /// Returns true if `c` is skipped when decoding, such as the hyphens
+    /// `base32::Crockford` allows for readability.
+    fn is_ignored(&self, _c: u8) -> bool {
+        false
+    }
+
+    /// Like `decode_partial_to`, but discards the partial output on failure.
+    fn decode_to(&self, dst: &mut [u8], src: &[u8]) -> Result<uint, ~str> {
+        match self.decode_partial_to(dst, src) {
+            Ok(n) => Ok(n),
+            Err((_, reason)) => Err(reason)
+        }
+    }
+
+    /// Encodes `src` into a newly allocated vector.
+    fn encode(&self, src: &[u8]) -> ~[u8] {
+        let dst_length = self.encoded_len(src.len());
+        let mut dst = vec::with_capacity(dst_length);
+        unsafe { vec::raw::set_len(&mut dst, dst_length); }
+        self.encode_to(dst, src);
+        dst
+    }
+
+    /// Encodes the first `n` bytes of `buf` over itself and returns the
+    /// encoded length. `buf` must be at least `encoded_len(n)` bytes long.
+    fn encode_in_place(&self, buf: &mut [u8], n: uint) -> uint {
+        let (src_block, dst_block) = self.block_len();
+        let dst_length = self.encoded_len(n);
+        assert!(buf.len() >= dst_length);
+
+        // Encoding quanta from the last one backwards never overwrites raw
+        // bytes that are still to be read.
+        let mut quantum = [0u8, ..8];
+        let mut q = (n + src_block - 1) / src_block;
+        while q > 0 {
+            q -= 1;
+            let start = q * src_block;
+            let len = cmp::min(src_block, n - start);
+            for i in range(0, len) {
+                quantum[i] = buf[start + i];
+            }
+            let dst_start = q * dst_block;
+            let m = self.encoded_len(len);
+            self.encode_to(buf.mut_slice(dst_start, dst_start + m), quantum.slice_to(len));
+        }
+
+        dst_length
+    }
+
+    /// Decodes `src` into a newly allocated vector, failing on bad input.
+    fn decode(&self, src: &[u8]) -> ~[u8] {
+        match self.decode_result(src) {
+            Ok(dst) => dst,
+            Err(reason) => fail!(reason)
+        }
+    }
+
+    /// Decodes `src` into a newly allocated vector.
+    fn decode_result(&self, src: &[u8]) -> Result<~[u8], ~str> {
+        let dst_length = self.decoded_len(src.len());
+        let mut dst = vec::with_capacity(dst_length);
+        unsafe { vec::raw::set_len(&mut dst, dst_length); }
+        match self.decode_to(dst, src) {
+            Ok(n) => unsafe { vec::raw::set_len(&mut dst, n) },
+            Err(reason) => return Err(reason)
+        }
+        Ok(dst)
+    }
+
+    /// Decodes `src` into a newly allocated vector. On failure, returns the
+    /// bytes decoded up to the last complete valid quantum along with the
+    /// reason.
+    fn decode_partial(&self, src: &[u8]) -> Result<~[u8], (~[u8], ~str)> {
+        let dst_length = self.decoded_len(src.len());
+        let mut dst = vec::with_capacity(dst_length);
+        unsafe { vec::raw::set_len(&mut dst, dst_length); }
+        match self.decode_partial_to(dst, src) {
+            Ok(n) => {
+                unsafe { vec::raw::set_len(&mut dst, n); }
+                Ok(dst)
+            }
+            Err((n, reason)) => {
+                unsafe { vec::raw::set_len(&mut dst, n); }
+                Err((dst, reason))
+            }
+        }
+    }
+
+    /// Returns true if `src` is well-formed.
+    fn is_valid(&self, src: &[u8]) -> bool {
+        self.validate(src).is_ok()
+    }
+
+    /// Decodes `src` into a fixed-size buffer such as a `[u8, ..32]` key,
+    /// failing unless it decodes to exactly `dst.len()` bytes.
+    fn decode_exact(&self, dst: &mut [u8], src: &[u8]) -> Result<(), ~str> {
+        let n = match self.validate(src) {
+            Ok(n) => n,
+            Err(reason) => return Err(reason)
+        };
+        if n != dst.len() {
+            return Err(format!("expected {} decoded bytes, got {}", dst.len(), n));
+        }
+        if n == 0 {
+            return Ok(());
+        }
+
+        // The last quantum may decode to fewer bytes than it takes up, so it
+        // goes through a stack buffer instead of straight into `dst`.
+        let (src_block, dst_block) = self.block_len();
+        let head = (n - 1) / src_block * src_block;
+        let nsplit = head / src_block * dst_block;
+        let mut split = 0;
+        let mut count = 0;
+        while count < nsplit {
+            if !self.is_ignored(src[split]) { count += 1; }
+            split += 1;
+        }
+        let mut quantum = [0u8, ..8];
+        match self.decode_to(dst.mut_slice_to(head), src.slice_to(split)) {
+            Ok(_) => (),
+            Err(reason) => return Err(reason)
+        }
+        match self.decode_to(quantum, src.slice_from(split)) {
+            Ok(_) => (),
+            Err(reason) => return Err(reason)
+        }
+        for i in range(head, n) {
+            dst[i] = quantum[i - head];
+        }
+
+        Ok(())
+    }
+
+    /// Encodes `src` into the start of `dst`, typically a stack array, and
+    /// returns the encoded text without allocating. `dst` must be at least
+    /// `encoded_len(src.len())` bytes long.
+    fn encode_to_str<'a>(&self, dst: &'a mut [u8], src: &[u8]) -> &'a str {
+        let n = self.encoded_len(src.len());
+        assert!(dst.len() >= n);
+        self.encode_to(dst.mut_slice_to(n), src);
+        str::from_utf8_slice(dst.slice_to(n))
+    }
+
+    /// Encodes `src` into a newly allocated string.
+    fn encode_to_string(&self, src: &[u8]) -> ~str {
+        util::encoded_to_string(self.encode(src))
+    }
+
+    /// Decodes the string `src`, failing on bad input.
+    fn decode_str(&self, src: &str) -> ~[u8] {
+        self.decode(src.as_bytes())
+    }
+
+    /// Decodes the string `src` into a newly allocated vector.
+    fn decode_str_result(&self, src: &str) -> Result<~[u8], ~str> {
+        self.decode_result(src.as_bytes())
+    }
+
+    /// Reads `r` until EOF and writes its encoded form to `w`.
+    fn encode_stream(&self, r: &mut Reader, w: &mut Writer) {
+        // whole quanta only, so that no chunk but the last is cut short
+        let (src_block, dst_block) = self.block_len();
+        let nblocks = STREAM_BUF_LEN / dst_block;
+        let src_length = nblocks * src_block;
+        let mut src = vec::from_elem(src_length, 0u8);
+        let mut dst = vec::from_elem(nblocks * dst_block, 0u8);
+
+        loop {
+            let n = fill(r, src);
+            if n == 0 { break; }
+            let m = self.encoded_len(n);
+            self.encode_to(dst.mut_slice_to(m), src.slice_to(n));
+            w.write(dst.slice_to(m));
+            if n < src_length { break; }
+        }
+    }
+
+    /// Reads encoded data from `r` until EOF and writes the decoded bytes
+    /// to `w`. Returns the number of bytes written.
+    fn decode_stream(&self, r: &mut Reader, w: &mut Writer) -> Result<uint, ~str> {
+        let (_, dst_block) = self.block_len();
+        let src_length = STREAM_BUF_LEN / dst_block * dst_block;
+        let mut src = vec::from_elem(src_length, 0u8);
+        let mut dst = vec::from_elem(self.decoded_len(src_length), 0u8);
+        let mut len = 0;
+        let mut ndecoded = 0;
+        let mut end = false;
+
+        loop {
+            let n = fill(r, src.mut_slice_from(len));
+            let eof = len + n < src.len();
+
+            // Ignored characters are dropped so that only whole quanta are
+            // decoded, and a quantum split across reads is carried over.
+            let mut k = len;
+            for i in range(len, len + n) {
+                if !self.is_ignored(src[i]) {
+                    src[k] = src[i];
+                    k += 1;
+                }
+            }
+            len = k;
+            if len == 0 {
+                if eof { break; }
+                continue;
+            }
+            if end {
+                return Err(~"unexpected data after padding");
+            }
+
+            let split = if eof { len } else { len / dst_block * dst_block };
+            let m = match self.decode_to(dst, src.slice_to(split)) {
+                Ok(m) => m,
+                Err(reason) => return Err(reason)
+            };
+            w.write(dst.slice_to(m));
+            ndecoded += m;
+            end = m < self.decoded_len(split);
+
+            for i in range(split, len) {
+                src[i - split] = src[i];
+            }
+            len -= split;
+            if eof { break; }
+        }
+
+        Ok(ndecoded)
+    }
+}
+
+/// Types that can be built from decoded bytes, such as keys or tokens, so
+/// that they can be decoded directly from encoded text with
+/// `from_base64_into` and friends.
+pub trait FromDecoded {
+    fn from_decoded(bytes: ~[u8]) -> Result<Self, ~str>;
+}
+
+// Reads from `r` until `buf` is full or EOF is reached.
+fn fill(r: &mut Reader, buf: &mut [u8]) -> uint {
+    let mut n = 0;
+    while n < buf.len() {
+        match r.read(buf.mut_slice_from(n)) {
+            Some(k) => n += k,
+            None => break
+        }
+    }
+    n
+}
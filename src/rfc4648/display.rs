@@ -0,0 +1,66 @@
+// display.rs
+
+use std::fmt;
+use std::uint;
+
+use codec::Codec;
+
+/// Formats bytes in their encoded form, encoding directly into the
+/// formatter in small stack chunks instead of allocating the whole output.
+///
+/// Both `{}` and `{:s}` print the encoding. `{:?}` goes through the
+/// reflection-based `fmt::Poly`, which has a blanket impl for every type and
+/// cannot be overridden, so it prints the struct fields instead.
+pub struct Display<'a, C> {
+    priv codec: C,
+    priv src: &'a [u8],
+    priv limit: Option<uint>,
+}
+
+impl<'a, C: Codec> Display<'a, C> {
+    /// Wraps `src` for formatting with `codec`; the `display` methods of
+    /// the codec types are shorthands for this.
+    pub fn new(codec: C, src: &'a [u8]) -> Display<'a, C> {
+        Display { codec: codec, src: src, limit: None }
+    }
+
+    /// Shows at most `n` encoded characters, followed by `...` if the
+    /// output was cut short.
+    pub fn truncate(self, n: uint) -> Display<'a, C> {
+        Display { limit: Some(n), ..self }
+    }
+}
+
+impl<'a, C: Codec> fmt::Default for Display<'a, C> {
+    fn fmt(d: &Display<'a, C>, f: &mut fmt::Formatter) {
+        write_encoded(d, f)
+    }
+}
+
+impl<'a, C: Codec> fmt::String for Display<'a, C> {
+    fn fmt(d: &Display<'a, C>, f: &mut fmt::Formatter) {
+        write_encoded(d, f)
+    }
+}
+
+// Writes the encoding of `d` into `f`, shared by the `{}` and `{:s}` impls.
+fn write_encoded<'a, C: Codec>(d: &Display<'a, C>, f: &mut fmt::Formatter) {
+    let mut buf = [0u8, ..64];
+    let chunk_len = d.codec.decoded_len(buf.len());
+    let mut remain = match d.limit {
+        Some(n) => n,
+        None => uint::max_value
+    };
+
+    for chunk in d.src.chunk_iter(chunk_len) {
+        let n = d.codec.encoded_len(chunk.len());
+        d.codec.encode_to(buf.mut_slice_to(n), chunk);
+        if n > remain {
+            f.buf.write(buf.slice_to(remain));
+            f.buf.write(bytes!("..."));
+            return;
+        }
+        f.buf.write(buf.slice_to(n));
+        remain -= n;
+    }
+}
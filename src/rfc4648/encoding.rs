@@ -0,0 +1,241 @@
+// encoding.rs
+
+use std::ascii::StrAsciiExt;
+use std::from_str::FromStr;
+use std::to_str::ToStr;
+
+use base16;
+use base32;
+use base45;
+use base58;
+use base64;
+use base85;
+use codec::Codec;
+
+/// Every codec and variant of this crate, selectable at runtime by name.
+///
+/// Canonical names follow the RFC 4648 section titles: `base64`,
+/// `base64url`, `base32`, `base32hex` and `base16`, plus `base32crockford`,
+/// `zbase32`, `base45`, `base58`, `base58flickr`, `base64bcrypt`,
+/// `base64crypt`, `ascii85`, `z85` and `base85git`. Lookup ignores case, `-`
+/// and `_`, and `hex`, `crockford`, `base58btc`, `bcrypt` and `crypt` are
+/// accepted as aliases.
+///
+/// Base58 and the base85 flavors have no `Codec`, as base58 has no fixed
+/// quanta and Ascii85 and git's base85 add framing around them; `codec`
+/// returns `None` for them.
+#[deriving(Eq, Clone)]
+pub enum Encoding {
+    Base16,
+    Base32,
+    Base32Hex,
+    Base32Crockford,
+    ZBase32,
+    Base45,
+    Base58Bitcoin,
+    Base58Flickr,
+    Base64,
+    Base64Url,
+    Base64Bcrypt,
+    Base64Crypt,
+    Ascii85,
+    Z85,
+    Base85Git,
+}
+
+static BASE16: base16::Base16Type = base16::Standard;
+static BASE32: base32::Base32Type = base32::Standard;
+static BASE32_HEX: base32::Base32Type = base32::Hex;
+static BASE32_CROCKFORD: base32::Base32Type = base32::Crockford;
+static ZBASE32: base32::Base32Type = base32::ZBase32;
+static BASE45: base45::Base45Type = base45::Standard;
+static BASE64: base64::Base64Type = base64::Standard;
+static BASE64_URL: base64::Base64Type = base64::UrlSafe;
+static BASE64_BCRYPT: base64::Base64Type = base64::Bcrypt;
+static BASE64_CRYPT: base64::Base64Type = base64::Crypt;
+
+// Where the operations of an `Encoding` are carried out.
+enum Backend {
+    Block(&'static Codec),
+    Base58(base58::Base58Type),
+    Base85(base85::Base85Type),
+}
+
+impl Encoding {
+    /// Looks up an encoding by its canonical name or a common alias.
+    pub fn from_name(name: &str) -> Option<Encoding> {
+        let key = name.to_ascii_lower().replace("-", "").replace("_", "");
+        match key.as_slice() {
+            "base16" | "hex" => Some(Base16),
+            "base32" => Some(Base32),
+            "base32hex" => Some(Base32Hex),
+            "base32crockford" | "crockford" => Some(Base32Crockford),
+            "zbase32" => Some(ZBase32),
+            "base45" => Some(Base45),
+            "base58" | "base58btc" => Some(Base58Bitcoin),
+            "base58flickr" => Some(Base58Flickr),
+            "base64" => Some(Base64),
+            "base64url" => Some(Base64Url),
+            "base64bcrypt" | "bcrypt" => Some(Base64Bcrypt),
+            "base64crypt" | "crypt" => Some(Base64Crypt),
+            "ascii85" => Some(Ascii85),
+            "z85" => Some(Z85),
+            "base85git" => Some(Base85Git),
+            _ => None
+        }
+    }
+
+    /// Returns the canonical name of the encoding.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Base16 => "base16",
+            Base32 => "base32",
+            Base32Hex => "base32hex",
+            Base32Crockford => "base32crockford",
+            ZBase32 => "zbase32",
+            Base45 => "base45",
+            Base58Bitcoin => "base58",
+            Base58Flickr => "base58flickr",
+            Base64 => "base64",
+            Base64Url => "base64url",
+            Base64Bcrypt => "base64bcrypt",
+            Base64Crypt => "base64crypt",
+            Ascii85 => "ascii85",
+            Z85 => "z85",
+            Base85Git => "base85git"
+        }
+    }
+
+    /// Encodes `src` into a newly allocated vector. Fails for `Z85` if the
+    /// length of `src` is not a multiple of 4; use `encode_result` to get
+    /// an error instead.
+    pub fn encode(self, src: &[u8]) -> ~[u8] {
+        match self.encode_result(src) {
+            Ok(dst) => dst,
+            Err(reason) => fail!(reason)
+        }
+    }
+    /// Encodes `src` into a newly allocated vector, or returns an error if
+    /// the encoding cannot represent it.
+    pub fn encode_result(self, src: &[u8]) -> Result<~[u8], ~str> {
+        match self.backend() {
+            Block(codec) => Ok(codec.encode(src)),
+            Base58(base58_type) => Ok(base58_type.encode(src)),
+            Base85(base85_type) => base85_type.encode_result(src)
+        }
+    }
+    /// Decodes `src` into a newly allocated vector, failing on bad input.
+    pub fn decode(self, src: &[u8]) -> ~[u8] {
+        match self.decode_result(src) {
+            Ok(dst) => dst,
+            Err(reason) => fail!(reason)
+        }
+    }
+    /// Decodes `src` into a newly allocated vector.
+    pub fn decode_result(self, src: &[u8]) -> Result<~[u8], ~str> {
+        match self.backend() {
+            Block(codec) => codec.decode_result(src),
+            Base58(base58_type) => base58_type.decode_result(src),
+            Base85(base85_type) => base85_type.decode_result(src)
+        }
+    }
+    /// Encodes `src` into a newly allocated string. Fails for `Z85` in the
+    /// same way as `encode`.
+    pub fn encode_to_string(self, src: &[u8]) -> ~str {
+        match self.backend() {
+            Block(codec) => codec.encode_to_string(src),
+            Base58(base58_type) => base58_type.encode_to_string(src),
+            Base85(base85_type) => base85_type.encode_to_string(src)
+        }
+    }
+    /// Decodes the string `src` into a newly allocated vector.
+    pub fn decode_str_result(self, src: &str) -> Result<~[u8], ~str> {
+        self.decode_result(src.as_bytes())
+    }
+    /// Checks `src` and returns its decoded length. Only the `Codec`
+    /// encodings check without producing output; the others are decoded in
+    /// full.
+    pub fn validate(self, src: &[u8]) -> Result<uint, ~str> {
+        match self.backend() {
+            Block(codec) => codec.validate(src),
+            _ => match self.decode_result(src) {
+                Ok(dst) => Ok(dst.len()),
+                Err(reason) => Err(reason)
+            }
+        }
+    }
+    /// Returns true if `src` is well-formed.
+    pub fn is_valid(self, src: &[u8]) -> bool {
+        self.validate(src).is_ok()
+    }
+
+    /// Decodes `src` and returns Rust source declaring the bytes as a
+    /// `static` array named `name`, twelve bytes to a line.
+    ///
+    /// Literals cannot be decoded at build time, as `macro_rules!` cannot
+    /// look inside a string and syntax extensions such as `bytes!` live in
+    /// the compiler. Embedded keys are instead generated with this once,
+    /// e.g. by the `static-gen` tool, and a test checks them against their
+    /// encoded text.
+    pub fn static_source(self, name: &str, src: &str) -> Result<~str, ~str> {
+        let dst = match self.decode_str_result(src) {
+            Ok(dst) => dst,
+            Err(reason) => return Err(reason)
+        };
+
+        let mut source = format!("static {}: [u8, ..{}] = [\n", name, dst.len());
+        for line in dst.chunk_iter(12) {
+            source.push_str("   ");
+            for &b in line.iter() {
+                source.push_str(" 0x");
+                source.push_char(base16::BASE16_TABLE[b >> 4] as char);
+                source.push_char(base16::BASE16_TABLE[b & 0xf] as char);
+                source.push_char(',');
+            }
+            source.push_char('\n');
+        }
+        source.push_str("];\n");
+        Ok(source)
+    }
+
+    /// Returns the `Codec` of the encoding, for generic code such as
+    /// `decode_stream` or `Display`, or `None` if it has no fixed quanta.
+    pub fn codec(&self) -> Option<&'static Codec> {
+        match self.backend() {
+            Block(codec) => Some(codec),
+            _ => None
+        }
+    }
+
+    fn backend(&self) -> Backend {
+        match *self {
+            Base16 => Block(&BASE16 as &'static Codec),
+            Base32 => Block(&BASE32 as &'static Codec),
+            Base32Hex => Block(&BASE32_HEX as &'static Codec),
+            Base32Crockford => Block(&BASE32_CROCKFORD as &'static Codec),
+            ZBase32 => Block(&ZBASE32 as &'static Codec),
+            Base45 => Block(&BASE45 as &'static Codec),
+            Base58Bitcoin => Base58(base58::Bitcoin),
+            Base58Flickr => Base58(base58::Flickr),
+            Base64 => Block(&BASE64 as &'static Codec),
+            Base64Url => Block(&BASE64_URL as &'static Codec),
+            Base64Bcrypt => Block(&BASE64_BCRYPT as &'static Codec),
+            Base64Crypt => Block(&BASE64_CRYPT as &'static Codec),
+            Ascii85 => Base85(base85::Ascii85),
+            Z85 => Base85(base85::Z85),
+            Base85Git => Base85(base85::Git)
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    fn from_str(s: &str) -> Option<Encoding> {
+        Encoding::from_name(s)
+    }
+}
+
+impl ToStr for Encoding {
+    fn to_str(&self) -> ~str {
+        self.name().to_owned()
+    }
+}
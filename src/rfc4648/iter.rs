@@ -0,0 +1,129 @@
+// iter.rs
+
+use codec::Codec;
+
+/// Lazily encodes the bytes of an iterator, yielding encoded characters.
+pub struct EncodeIter<C, I> {
+    priv codec: C,
+    priv iter: I,
+    priv buf: [u8, ..8],
+    priv pos: uint,
+    priv len: uint,
+}
+
+/// Lazily decodes the characters of an iterator, yielding decoded bytes.
+/// Byte positions in errors are relative to the failing quantum.
+pub struct DecodeIter<C, I> {
+    priv codec: C,
+    priv iter: I,
+    priv buf: [u8, ..8],
+    priv pos: uint,
+    priv len: uint,
+    priv padded: bool,
+    priv done: bool,
+}
+
+/// Returns an iterator over the encoded form of the bytes of `iter`,
+/// encoding one quantum at a time.
+pub fn encode<C: Codec, I: Iterator<u8>>(codec: C, iter: I) -> EncodeIter<C, I> {
+    EncodeIter { codec: codec, iter: iter, buf: [0u8, ..8], pos: 0, len: 0 }
+}
+
+/// Returns an iterator over the bytes decoded from the encoded
+/// characters of `iter`, yielding an error and then stopping on bad
+/// input.
+pub fn decode<C: Codec, I: Iterator<u8>>(codec: C, iter: I) -> DecodeIter<C, I> {
+    DecodeIter {
+        codec: codec,
+        iter: iter,
+        buf: [0u8, ..8],
+        pos: 0,
+        len: 0,
+        padded: false,
+        done: false,
+    }
+}
+
+impl<C: Codec, I: Iterator<u8>> Iterator<u8> for EncodeIter<C, I> {
+    fn next(&mut self) -> Option<u8> {
+        if self.pos == self.len {
+            let (src_block, _) = self.codec.block_len();
+            let mut quantum = [0u8, ..8];
+            let n = fill(&mut self.iter, quantum.mut_slice_to(src_block));
+            if n == 0 {
+                return None;
+            }
+            let len = self.codec.encoded_len(n);
+            self.codec.encode_to(self.buf.mut_slice_to(len), quantum.slice_to(n));
+            self.pos = 0;
+            self.len = len;
+        }
+        self.pos += 1;
+        Some(self.buf[self.pos - 1])
+    }
+}
+
+impl<C: Codec, I: Iterator<u8>> Iterator<Result<u8, ~str>> for DecodeIter<C, I> {
+    fn next(&mut self) -> Option<Result<u8, ~str>> {
+        while self.pos == self.len {
+            if self.done {
+                return None;
+            }
+            let (_, dst_block) = self.codec.block_len();
+            let mut quantum = [0u8, ..8];
+            let n = fill_significant(&self.codec, &mut self.iter,
+                                     quantum.mut_slice_to(dst_block));
+            if n == 0 {
+                self.done = true;
+                return None;
+            }
+            if self.padded {
+                self.done = true;
+                return Some(Err(~"trailing data after padding"));
+            }
+            match self.codec.decode_to(self.buf, quantum.slice_to(n)) {
+                Ok(len) => {
+                    self.padded = len < self.codec.decoded_len(n);
+                    self.pos = 0;
+                    self.len = len;
+                }
+                Err(reason) => {
+                    self.done = true;
+                    return Some(Err(reason));
+                }
+            }
+        }
+        self.pos += 1;
+        Some(Ok(self.buf[self.pos - 1]))
+    }
+}
+
+// Pulls bytes from `iter` until `buf` is full or the iterator is exhausted.
+fn fill<I: Iterator<u8>>(iter: &mut I, buf: &mut [u8]) -> uint {
+    let mut n = 0;
+    while n < buf.len() {
+        match iter.next() {
+            Some(b) => buf[n] = b,
+            None => break
+        }
+        n += 1;
+    }
+    n
+}
+
+// Like `fill`, but drops the characters `codec` ignores, so that `n`
+// counts only the characters of the quantum.
+fn fill_significant<C: Codec, I: Iterator<u8>>(codec: &C, iter: &mut I, buf: &mut [u8]) -> uint {
+    let mut n = 0;
+    while n < buf.len() {
+        match iter.next() {
+            Some(b) if codec.is_ignored(b) => (),
+            Some(b) => {
+                buf[n] = b;
+                n += 1;
+            }
+            None => break
+        }
+    }
+    n
+}
@@ -6,7 +6,24 @@
 #[comment = "RFC 4648: Base16, Base32, Base64 Data Encodings"];
 #[license = "MIT license"];
 #[crate_type = "lib"];
+#[feature(macro_rules)];
+
+extern mod extra;
 
 pub mod base16;
 pub mod base32;
+pub mod base45;
+pub mod base58;
 pub mod base64;
+pub mod base85;
+pub mod bech32;
+pub mod codec;
+pub mod display;
+pub mod encoding;
+pub mod iter;
+pub mod quoted_printable;
+#[cfg(serialize)]
+pub mod serialize;
+pub mod uuencode;
+pub mod validated;
+mod util;
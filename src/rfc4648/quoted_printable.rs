@@ -0,0 +1,198 @@
+// quoted_printable.rs
+
+use std::vec;
+
+use base16::{BASE16_TABLE, BASE16_DECODE_MAP};
+use util;
+
+/// Quoted-Printable modes (RFC 2045). `Text` turns the line breaks of the
+/// input, CRLF or LF, into CRLF hard line breaks; `Binary` escapes CR and LF
+/// like any other byte so that the data survives unchanged.
+#[deriving(Eq, Clone)]
+pub enum QpMode {
+    Text,
+    Binary,
+}
+
+static MAX_LINE_LEN: uint = 76;
+static EQUALS: u8 = 61; // '='
+static SPACE: u8 = 32;
+static TAB: u8 = 9;
+static CR: u8 = 13;
+static LF: u8 = 10;
+static CRLF: &'static [u8] = bytes!("\r\n");
+static SOFT_BREAK: &'static [u8] = bytes!("=\r\n");
+
+/// Encodes `src` with lines of at most 76 characters, breaking longer ones
+/// with `=` soft line breaks. Whitespace at the end of a line is escaped so
+/// that transports stripping it do not change the data.
+pub fn encode(src: &[u8], mode: QpMode) -> ~[u8] {
+    let len = src.len();
+    let mut dst = vec::with_capacity(len + len / 2);
+    let mut col = 0;
+    let mut i = 0;
+
+    while i < len {
+        let b = src[i];
+        if mode == Text && is_line_break(src, i) {
+            dst.push_all(CRLF);
+            col = 0;
+            i += if b == CR { 2 } else { 1 };
+            continue;
+        }
+
+        let at_line_end = i + 1 == len || (mode == Text && is_line_break(src, i + 1));
+        let literal = (b >= 33 && b <= 126 && b != EQUALS)
+            || (is_space(b) && !at_line_end);
+        let width = if literal { 1 } else { 3 };
+
+        // leave room for the `=` of a soft line break, which the last
+        // character of a line does not need
+        let limit = if at_line_end { MAX_LINE_LEN } else { MAX_LINE_LEN - 1 };
+        if col + width > limit {
+            dst.push_all(SOFT_BREAK);
+            col = 0;
+        }
+
+        if literal {
+            dst.push(b);
+        } else {
+            dst.push(EQUALS);
+            dst.push(BASE16_TABLE[b >> 4]);
+            dst.push(BASE16_TABLE[b & 0x0f]);
+        }
+        col += width;
+        i += 1;
+    }
+
+    dst
+}
+
+/// Encodes `src` in `mode` into a newly allocated string.
+pub fn encode_to_string(src: &[u8], mode: QpMode) -> ~str {
+    util::encoded_to_string(encode(src, mode))
+}
+
+/// Decodes `src` leniently, as RFC 2045 recommends for robustness: soft line
+/// breaks may be preceded by whitespace, escapes may be in lowercase,
+/// malformed escapes are kept as they are and whitespace at the end of a
+/// line is dropped. Line breaks are kept as they are.
+pub fn decode(src: &[u8]) -> ~[u8] {
+    let len = src.len();
+    let mut dst = vec::with_capacity(len);
+    let mut i = 0;
+
+    while i < len {
+        let b = src[i];
+        if b == EQUALS {
+            let j = skip_space(src, i + 1);
+            if j == len {
+                // a soft line break at the end of the data
+                i = len;
+            } else if is_line_break(src, j) {
+                i = j + if src[j] == CR { 2 } else { 1 };
+            } else if i + 2 < len && is_hex(src[i+1], false) && is_hex(src[i+2], false) {
+                dst.push(hex_value(src[i+1], src[i+2]));
+                i += 3;
+            } else {
+                dst.push(b);
+                i += 1;
+            }
+        } else if is_space(b) {
+            let j = skip_space(src, i);
+            if j < len && !is_line_break(src, j) {
+                dst.push_all(src.slice(i, j));
+            }
+            i = j;
+        } else {
+            dst.push(b);
+            i += 1;
+        }
+    }
+
+    dst
+}
+
+/// Decodes `src`, failing on anything RFC 2045 does not allow: malformed or
+/// lowercase escapes, control characters other than CRLF, LF and tab,
+/// whitespace at the end of a line and lines longer than 76 characters.
+pub fn decode_result(src: &[u8]) -> Result<~[u8], ~str> {
+    let len = src.len();
+    let mut dst = vec::with_capacity(len);
+    let mut line_start = 0;
+    let mut i = 0;
+
+    while i < len {
+        let b = src[i];
+        if is_line_break(src, i) {
+            if i - line_start > MAX_LINE_LEN {
+                return Err(format!("quoted-printable line too long at input byte {}", line_start));
+            }
+            let n = if b == CR { 2 } else { 1 };
+            dst.push_all(src.slice(i, i + n));
+            i += n;
+            line_start = i;
+        } else if b == EQUALS {
+            if i + 1 < len && is_line_break(src, i + 1) {
+                if i + 1 - line_start > MAX_LINE_LEN {
+                    return Err(format!("quoted-printable line too long at input byte {}", line_start));
+                }
+                i += if src[i+1] == CR { 3 } else { 2 };
+                line_start = i;
+            } else if i + 2 < len && is_hex(src[i+1], true) && is_hex(src[i+2], true) {
+                dst.push(hex_value(src[i+1], src[i+2]));
+                i += 3;
+            } else {
+                return Err(fail_decode_at(i));
+            }
+        } else if is_space(b) {
+            let j = skip_space(src, i);
+            if j == len || is_line_break(src, j) {
+                return Err(format!("trailing whitespace at input byte {}", i));
+            }
+            dst.push_all(src.slice(i, j));
+            i = j;
+        } else if b >= 33 && b <= 126 {
+            dst.push(b);
+            i += 1;
+        } else {
+            return Err(fail_decode_at(i));
+        }
+    }
+
+    if len - line_start > MAX_LINE_LEN {
+        return Err(format!("quoted-printable line too long at input byte {}", line_start));
+    }
+
+    Ok(dst)
+}
+
+// A CRLF or LF at `i`.
+fn is_line_break(src: &[u8], i: uint) -> bool {
+    i < src.len() && (src[i] == LF || (src[i] == CR && i + 1 < src.len() && src[i+1] == LF))
+}
+
+fn is_space(b: u8) -> bool {
+    b == SPACE || b == TAB
+}
+
+fn skip_space(src: &[u8], i: uint) -> uint {
+    let mut i = i;
+    while i < src.len() && is_space(src[i]) {
+        i += 1;
+    }
+    i
+}
+
+// RFC 2045 requires uppercase hex digits, which `strict` enforces.
+fn is_hex(c: u8, strict: bool) -> bool {
+    BASE16_DECODE_MAP[c] != 0xff && !(strict && c >= 97)
+}
+
+fn hex_value(a: u8, b: u8) -> u8 {
+    BASE16_DECODE_MAP[a] << 4 | BASE16_DECODE_MAP[b]
+}
+
+fn fail_decode_at(n: uint) -> ~str {
+    format!("illegal quoted-printable data at input byte {}", n)
+}
@@ -0,0 +1,154 @@
+// serialize.rs
+//
+// Byte fields that serialize as encoded strings, for use with
+// `#[deriving(Encodable, Decodable)]`. Built with `--cfg serialize`, see
+// `make test-serialize`.
+//
+//     #[deriving(Encodable, Decodable)]
+//     struct Credential {
+//         key: Base64UrlBytes,
+//         digest: Option<Base16Bytes>,
+//         nonce: Base64Array16,
+//     }
+//
+// The `*Array*` types hold a fixed number of bytes, for keys and digests,
+// and reject text that decodes to any other length.
+
+use extra::serialize::{Encodable, Decodable, Encoder, Decoder};
+
+use base16;
+use base32;
+use base45;
+use base64;
+use codec::Codec;
+
+// `$unpadded` leaves the `=` padding off the encoded string, and rejects it
+// when decoding.
+macro_rules! encoded_bytes(
+    ($name:ident, $codec:expr, $label:expr, $unpadded:expr) => (
+        #[deriving(Eq, Clone)]
+        pub struct $name(~[u8]);
+
+        impl<S: Encoder> Encodable<S> for $name {
+            fn encode(&self, s: &mut S) {
+                let $name(ref bytes) = *self;
+                s.emit_str(encode_field(&$codec as &Codec, *bytes, $unpadded));
+            }
+        }
+
+        impl<D: Decoder> Decodable<D> for $name {
+            fn decode(d: &mut D) -> $name {
+                let text = d.read_str();
+                match decode_field(&$codec as &Codec, text, $unpadded) {
+                    Ok(bytes) => $name(bytes),
+                    Err(reason) => fail!(format!("invalid {} string \"{}\": {}", $label, text, reason))
+                }
+            }
+        }
+    )
+)
+
+macro_rules! encoded_array(
+    ($name:ident, $codec:expr, $label:expr, $unpadded:expr, $len:expr) => (
+        pub struct $name([u8, ..$len]);
+
+        impl Eq for $name {
+            fn eq(&self, other: &$name) -> bool {
+                let $name(ref a) = *self;
+                let $name(ref b) = *other;
+                a.as_slice() == b.as_slice()
+            }
+        }
+
+        impl Clone for $name {
+            fn clone(&self) -> $name {
+                let $name(bytes) = *self;
+                $name(bytes)
+            }
+        }
+
+        impl<S: Encoder> Encodable<S> for $name {
+            fn encode(&self, s: &mut S) {
+                let $name(ref bytes) = *self;
+                s.emit_str(encode_field(&$codec as &Codec, bytes.as_slice(), $unpadded));
+            }
+        }
+
+        impl<D: Decoder> Decodable<D> for $name {
+            fn decode(d: &mut D) -> $name {
+                let text = d.read_str();
+                let mut bytes = [0u8, ..$len];
+                match decode_array(&$codec as &Codec, text, $unpadded, bytes) {
+                    Ok(()) => $name(bytes),
+                    Err(reason) => fail!(format!("invalid {} string \"{}\": {}", $label, text, reason))
+                }
+            }
+        }
+    )
+)
+
+encoded_bytes!(Base16Bytes, base16::Standard, "base16", false)
+encoded_bytes!(Base32Bytes, base32::Standard, "base32", false)
+encoded_bytes!(Base32UnpaddedBytes, base32::Standard, "unpadded base32", true)
+encoded_bytes!(Base32HexBytes, base32::Hex, "base32hex", false)
+encoded_bytes!(Base32HexUnpaddedBytes, base32::Hex, "unpadded base32hex", true)
+encoded_bytes!(Base32CrockfordBytes, base32::Crockford, "base32crockford", false)
+encoded_bytes!(ZBase32Bytes, base32::ZBase32, "zbase32", false)
+encoded_bytes!(Base64Bytes, base64::Standard, "base64", false)
+encoded_bytes!(Base64UnpaddedBytes, base64::Standard, "unpadded base64", true)
+encoded_bytes!(Base64UrlBytes, base64::UrlSafe, "base64url", false)
+encoded_bytes!(Base64UrlUnpaddedBytes, base64::UrlSafe, "unpadded base64url", true)
+encoded_bytes!(Base64BcryptBytes, base64::Bcrypt, "base64bcrypt", false)
+encoded_bytes!(Base64CryptBytes, base64::Crypt, "base64crypt", false)
+encoded_bytes!(Base45Bytes, base45::Standard, "base45", false)
+
+encoded_array!(Base16Array16, base16::Standard, "base16", false, 16)
+encoded_array!(Base16Array20, base16::Standard, "base16", false, 20)
+encoded_array!(Base16Array32, base16::Standard, "base16", false, 32)
+encoded_array!(Base16Array64, base16::Standard, "base16", false, 64)
+encoded_array!(Base64Array16, base64::Standard, "base64", false, 16)
+encoded_array!(Base64Array20, base64::Standard, "base64", false, 20)
+encoded_array!(Base64Array32, base64::Standard, "base64", false, 32)
+encoded_array!(Base64Array64, base64::Standard, "base64", false, 64)
+encoded_array!(Base64UrlUnpaddedArray16, base64::UrlSafe, "unpadded base64url", true, 16)
+encoded_array!(Base64UrlUnpaddedArray20, base64::UrlSafe, "unpadded base64url", true, 20)
+encoded_array!(Base64UrlUnpaddedArray32, base64::UrlSafe, "unpadded base64url", true, 32)
+encoded_array!(Base64UrlUnpaddedArray64, base64::UrlSafe, "unpadded base64url", true, 64)
+
+fn encode_field(codec: &Codec, bytes: &[u8], unpadded: bool) -> ~str {
+    let text = codec.encode_to_string(bytes);
+    if unpadded {
+        text.trim_right_chars(&'=').to_owned()
+    } else {
+        text
+    }
+}
+
+fn decode_field(codec: &Codec, text: &str, unpadded: bool) -> Result<~[u8], ~str> {
+    if !unpadded {
+        return codec.decode_str_result(text);
+    }
+    match text.find('=') {
+        Some(i) => return Err(format!("unexpected padding at input byte {}", i)),
+        None => ()
+    }
+
+    let (_, dst_block) = codec.block_len();
+    let mut padded = text.to_owned();
+    while padded.len() % dst_block != 0 {
+        padded.push_char('=');
+    }
+    codec.decode_str_result(padded)
+}
+
+fn decode_array(codec: &Codec, text: &str, unpadded: bool, dst: &mut [u8]) -> Result<(), ~str> {
+    let bytes = match decode_field(codec, text, unpadded) {
+        Ok(bytes) => bytes,
+        Err(reason) => return Err(reason)
+    };
+    if bytes.len() != dst.len() {
+        return Err(format!("expected {} decoded bytes, got {}", dst.len(), bytes.len()));
+    }
+    dst.copy_from(bytes);
+    Ok(())
+}
@@ -1,10 +1,32 @@
 extern mod rfc4648;
+#[cfg(serialize)]
+extern mod extra;
 
 use std::vec;
+use std::from_str::from_str;
+use std::rt::io::{Reader, Writer, Decorator};
+use std::rt::io::mem::{MemReader, MemWriter};
 
 use rfc4648::base16;
 use rfc4648::base32;
+use rfc4648::base45;
+use rfc4648::base58;
 use rfc4648::base64;
+use rfc4648::base85;
+use rfc4648::bech32;
+use rfc4648::codec::{Codec, FromDecoded};
+use rfc4648::base16::{ToBase16, FromBase16};
+use rfc4648::base32::{ToBase32, FromBase32};
+use rfc4648::base64::{ToBase64, FromBase64};
+use rfc4648::encoding;
+use rfc4648::encoding::Encoding;
+use rfc4648::iter;
+use rfc4648::quoted_printable;
+use rfc4648::uuencode;
+use rfc4648::uuencode::UuWriter;
+use rfc4648::validated;
+use rfc4648::validated::{EncodedString, EncodedStr, Base16Str, Base64String, Base64UrlString,
+                         Base64UrlStr};
 
 fn t(source: ~[~str], expect: ~[~str], cb: &fn(&[u8]) -> ~[u8]) {
     let mut source_b = vec::with_capacity(source.len());
@@ -121,3 +143,1020 @@ fn test_base16_decode() {
 
     assert_eq!(expect, actual.as_slice());
 }
+
+fn roundtrip<C: Codec>(codec: C, src: &[u8]) -> ~[u8] {
+    codec.decode(codec.encode(src))
+}
+
+#[test]
+fn test_codec_roundtrip() {
+    let source = bytes!("fo?ba?");
+
+    assert_eq!(source, roundtrip(base16::Standard, source).as_slice());
+    assert_eq!(source, roundtrip(base32::Standard, source).as_slice());
+    assert_eq!(source, roundtrip(base32::Hex, source).as_slice());
+    assert_eq!(source, roundtrip(base64::Standard, source).as_slice());
+    assert_eq!(source, roundtrip(base64::UrlSafe, source).as_slice());
+}
+
+#[test]
+fn test_codec_encode_stream() {
+    let mut reader = MemReader::new(bytes!("foobar").to_owned());
+    let mut writer = MemWriter::new();
+
+    base32::Standard.encode_stream(&mut reader as &mut Reader, &mut writer as &mut Writer);
+
+    assert_eq!(bytes!("MZXW6YTBOI======"), writer.inner().as_slice());
+}
+
+#[test]
+fn test_codec_decode_stream() {
+    let mut reader = MemReader::new(bytes!("Zm9vYmFy").to_owned());
+    let mut writer = MemWriter::new();
+
+    let n = base64::Standard.decode_stream(&mut reader as &mut Reader, &mut writer as &mut Writer);
+
+    assert_eq!(Ok(6), n);
+    assert_eq!(bytes!("foobar"), writer.inner().as_slice());
+}
+
+#[test]
+fn test_encoding_names() {
+    assert_eq!(Some(encoding::Base64Url), from_str::<Encoding>("base64url"));
+    assert_eq!(Some(encoding::Base32Hex), from_str::<Encoding>("Base32-Hex"));
+    assert_eq!(Some(encoding::Base16), from_str::<Encoding>("hex"));
+    assert_eq!(Some(encoding::Base58Bitcoin), from_str::<Encoding>("base58"));
+    assert_eq!(Some(encoding::Base58Flickr), from_str::<Encoding>("Base58-Flickr"));
+    assert_eq!(None, from_str::<Encoding>("base62"));
+
+    assert_eq!(~"base64url", encoding::Base64Url.to_str());
+    assert_eq!(~"base16", encoding::Base16.to_str());
+}
+
+static FOOBAR: [u8, ..6] = [
+    0x66, 0x6F, 0x6F, 0x62, 0x61, 0x72,
+];
+
+#[test]
+fn test_encoding_static_source() {
+    let expect = ~"static FOOBAR: [u8, ..6] = [\n    0x66, 0x6F, 0x6F, 0x62, 0x61, 0x72,\n];\n";
+    assert_eq!(Ok(expect), encoding::Base64.static_source("FOOBAR", "Zm9vYmFy"));
+    assert_eq!(Ok(~"static EMPTY: [u8, ..0] = [\n];\n"), encoding::Base16.static_source("EMPTY", ""));
+    assert_eq!(Err(~"illegal base16 byte 122"), encoding::Base16.static_source("BAD", "66zz"));
+
+    // the generated source above, checked against its text
+    assert_eq!(~"Zm9vYmFy", base64::Standard.encode_to_string(FOOBAR));
+}
+
+#[test]
+fn test_encoding_dispatch() {
+    let encoding: Encoding = from_str("base32hex").unwrap();
+
+    assert_eq!(bytes!("CPNMUOJ1E8======"), encoding.encode(bytes!("foobar")).as_slice());
+    assert_eq!(bytes!("foobar"), encoding.decode(bytes!("CPNMUOJ1E8======")).as_slice());
+}
+
+#[test]
+fn test_display() {
+    assert_eq!(~"Zm9vYmFy", format!("{}", base64::Standard.display(bytes!("foobar"))));
+    assert_eq!(~"MZXW6YTBOI======", format!("{}", base32::Standard.display(bytes!("foobar"))));
+    assert_eq!(~"", format!("{}", base16::Standard.display([])));
+    assert_eq!(~"Zm9vYmFy", format!("{:s}", base64::Standard.display(bytes!("foobar"))));
+
+    let source = vec::from_elem(40, 0xabu8);
+    let expect = vec::from_elem(40, ~"AB").concat();
+    assert_eq!(expect, format!("{}", base16::Standard.display(source)));
+}
+
+#[test]
+fn test_display_truncate() {
+    let source = bytes!("foobar");
+
+    assert_eq!(~"Zm9v...", format!("{}", base64::Standard.display(source).truncate(4)));
+    assert_eq!(~"Zm9vYmFy", format!("{}", base64::Standard.display(source).truncate(8)));
+    assert_eq!(~"...", format!("{}", base16::Standard.display(source).truncate(0)));
+}
+
+#[test]
+fn test_encode_to_string() {
+    assert_eq!(~"Zm8_YmE_", base64::UrlSafe.encode_to_string(bytes!("fo?ba?")));
+    assert_eq!(~"MZXW6YQ=", base32::Standard.encode_to_string(bytes!("foob")));
+    assert_eq!(~"666F6F", base16::encode_to_string(bytes!("foo")));
+    assert_eq!(~"CPNG====", encoding::Base32Hex.encode_to_string(bytes!("fo")));
+}
+
+#[test]
+fn test_decode_str() {
+    assert_eq!(bytes!("fo?ba?"), base64::UrlSafe.decode_str("Zm8_YmE_").as_slice());
+    assert_eq!(bytes!("foob"), base32::Standard.decode_str("MZXW6YQ=").as_slice());
+    assert_eq!(bytes!("foo"), base16::decode_str("666f6f").as_slice());
+    assert!(base64::Standard.decode_str_result("Zm8_YmE_").is_err());
+    assert!(base16::decode_str_result("666").is_err());
+}
+
+#[test]
+fn test_validate() {
+    assert_eq!(Ok(6), base64::Standard.validate(bytes!("Zm9vYmFy")));
+    assert_eq!(Ok(4), base64::Standard.validate(bytes!("Zm9vYg==")));
+    assert_eq!(Ok(3), base32::Hex.validate(bytes!("CPNMU===")));
+    assert_eq!(Ok(3), base16::validate(bytes!("666f6f")));
+    assert_eq!(Ok(0), base64::UrlSafe.validate([]));
+
+    assert!(base64::Standard.validate(bytes!("Zm8_YmE_")).is_err());
+    assert!(base32::Standard.validate(bytes!("MZXW6YQ")).is_err());
+    assert!(base16::validate(bytes!("66zz")).is_err());
+}
+
+#[test]
+fn test_is_valid() {
+    assert!(base64::UrlSafe.is_valid(bytes!("Zm8_YmE_")));
+    assert!(!base64::UrlSafe.is_valid(bytes!("Zm8/YmE/")));
+    assert!(base32::Standard.is_valid(bytes!("MZXW6YTBOI======")));
+    assert!(!base16::is_valid(bytes!("666")));
+    assert!(encoding::Base32Hex.is_valid(bytes!("CPNMUOJ1")));
+}
+
+#[test]
+fn test_decode_to_short_dst() {
+    let mut dst = [0u8, ..4];
+    assert_eq!(Err((3, ~"output buffer of 4 bytes is too small")),
+               base64::Standard.decode_partial_to(dst, bytes!("Zm9vYmFy")));
+    assert_eq!(bytes!("foo"), dst.slice_to(3));
+    assert_eq!(Err(~"output buffer of 0 bytes is too small"),
+               base32::Standard.decode_to([], bytes!("MZXW6===")));
+    assert_eq!(Err(~"output buffer of 2 bytes is too small"),
+               base16::Standard.decode_to(dst.mut_slice_to(2), bytes!("666f6f")));
+    assert_eq!(Err(~"output buffer of 1 bytes is too small"),
+               base45::Standard.decode_to(dst.mut_slice_to(1), bytes!("BB8")));
+    assert_eq!(Err(~"output buffer of 0 bytes is too small"),
+               base64::Crypt.decode_to([], bytes!("7E")));
+    assert_eq!(Ok(0), base64::Standard.decode_to([], []));
+}
+
+fn partial(result: Result<~[u8], (~[u8], ~str)>) -> ~[u8] {
+    match result {
+        Ok(_) => fail!(~"decoding should have failed"),
+        Err((dst, _)) => dst
+    }
+}
+
+#[test]
+fn test_decode_partial() {
+    assert_eq!(Ok(bytes!("foobar").to_owned()), base64::Standard.decode_partial(bytes!("Zm9vYmFy")));
+
+    assert_eq!(bytes!("foo"), partial(base64::Standard.decode_partial(bytes!("Zm9vYm"))).as_slice());
+    assert_eq!(bytes!("foo"), partial(base64::Standard.decode_partial(bytes!("Zm9v!mFy"))).as_slice());
+    assert_eq!(bytes!(""), partial(base64::UrlSafe.decode_partial(bytes!("Zm8/"))).as_slice());
+    assert_eq!(bytes!("fooba"), partial(base32::Standard.decode_partial(bytes!("MZXW6YTBOI"))).as_slice());
+    assert_eq!(bytes!("fo"), partial(base16::decode_partial(bytes!("666f6"))).as_slice());
+    assert_eq!(bytes!("f"), partial(base16::decode_partial(bytes!("66zz6f"))).as_slice());
+}
+
+#[test]
+fn test_decode_length_error_first() {
+    // a bad length is reported over an illegal byte in the complete quanta
+    assert_eq!(Err(~"odd length base16 data"), base16::decode_result(bytes!("66zz6")));
+    assert_eq!(Err(~"the input length should be divisible by 8"),
+               base32::Standard.decode_result(bytes!("MZXW6YT!OI")));
+    assert_eq!(Err(~"the input length should be divisible by 4"),
+               base64::Standard.decode_result(bytes!("Zm9v!mFyY")));
+
+    // while the bytes before the illegal one are still recovered
+    assert_eq!(Err((bytes!("f").to_owned(), ~"odd length base16 data")),
+               base16::decode_partial(bytes!("66zz6")));
+    assert_eq!(Err((bytes!("foo").to_owned(), ~"the input length should be divisible by 4")),
+               base64::Standard.decode_partial(bytes!("Zm9v!mFyY")));
+}
+
+#[test]
+fn test_base64_decode_concat() {
+    let source = bytes!("Zg==Zm8=Zm9v");
+
+    assert_eq!(bytes!("ffofoo"), base64::Standard.decode_concat(source).as_slice());
+    assert_eq!(bytes!("foobar"), base64::Standard.decode_concat(bytes!("Zm9vYmFy")).as_slice());
+    assert_eq!(Err(~"trailing data after padding at input byte 4"),
+               base64::Standard.decode_result(source));
+}
+
+#[test]
+fn test_base32_decode_concat() {
+    let source = bytes!("MY======MZXQ====");
+
+    assert_eq!(bytes!("ffo"), base32::Standard.decode_concat(source).as_slice());
+    assert_eq!(Err(~"trailing data after padding at input byte 8"),
+               base32::Standard.decode_result(source));
+}
+
+#[test]
+fn test_decode_in_place() {
+    let mut buf = bytes!("Zm9vYmE=").to_owned();
+    let n = base64::Standard.decode_in_place(buf);
+    assert_eq!(Ok(5), n);
+    assert_eq!(bytes!("fooba"), buf.slice_to(5));
+
+    let mut buf = bytes!("MZXW6YTBOI======").to_owned();
+    let n = base32::Standard.decode_in_place(buf);
+    assert_eq!(Ok(6), n);
+    assert_eq!(bytes!("foobar"), buf.slice_to(6));
+
+    let mut buf = bytes!("666f6f").to_owned();
+    let n = base16::decode_in_place(buf);
+    assert_eq!(Ok(3), n);
+    assert_eq!(bytes!("foo"), buf.slice_to(3));
+
+    let mut buf = bytes!("CSQP-YRK1-E8").to_owned();
+    let n = base32::Crockford.decode_in_place(buf);
+    assert_eq!(Ok(6), n);
+    assert_eq!(bytes!("foobar"), buf.slice_to(6));
+
+    let mut buf = bytes!("Zm9v!mFy").to_owned();
+    assert_eq!(Err((3, ~"illegal base64 data at input byte 4")),
+               base64::Standard.decode_in_place(buf));
+    assert_eq!(bytes!("foo"), buf.slice_to(3));
+
+    let mut buf = bytes!("MZXW6===MZXW6YQ=").to_owned();
+    assert_eq!(Err((3, ~"trailing data after padding at input byte 8")),
+               base32::Standard.decode_in_place(buf));
+    assert_eq!(bytes!("foo"), buf.slice_to(3));
+
+    let mut buf = bytes!("666f6g").to_owned();
+    assert_eq!(Err((2, ~"illegal base16 byte 103")), base16::decode_in_place(buf));
+    assert_eq!(bytes!("fo"), buf.slice_to(2));
+
+    let mut buf = bytes!("%69 VD92EX0").to_owned();
+    assert_eq!(Ok(7), base45::decode_in_place(buf));
+    assert_eq!(bytes!("Hello!!"), buf.slice_to(7));
+
+    let mut buf = base64::Crypt.encode(bytes!("foobar!"));
+    assert_eq!(Ok(7), base64::Crypt.decode_in_place(buf));
+    assert_eq!(bytes!("foobar!"), buf.slice_to(7));
+}
+
+#[test]
+fn test_encode_in_place() {
+    let mut buf = vec::from_elem(8, 0u8);
+    buf.mut_slice_to(5).copy_from(bytes!("fooba"));
+    assert_eq!(8, base64::Standard.encode_in_place(buf, 5));
+    assert_eq!(bytes!("Zm9vYmE="), buf.as_slice());
+
+    let mut buf = vec::from_elem(16, 0u8);
+    buf.mut_slice_to(6).copy_from(bytes!("foobar"));
+    assert_eq!(16, base32::Hex.encode_in_place(buf, 6));
+    assert_eq!(bytes!("CPNMUOJ1E8======"), buf.as_slice());
+
+    let mut buf = vec::from_elem(6, 0u8);
+    buf.mut_slice_to(3).copy_from(bytes!("foo"));
+    assert_eq!(6, base16::encode_in_place(buf, 3));
+    assert_eq!(bytes!("666F6F"), buf.as_slice());
+}
+
+#[test]
+fn test_decode_exact() {
+    let mut key = [0u8, ..5];
+    assert_eq!(Ok(()), base64::Standard.decode_exact(key, bytes!("Zm9vYmE=")));
+    assert_eq!(bytes!("fooba"), key.as_slice());
+
+    let mut uuid = [0u8, ..6];
+    assert_eq!(Ok(()), base32::Standard.decode_exact(uuid, bytes!("MZXW6YTBOI======")));
+    assert_eq!(bytes!("foobar"), uuid.as_slice());
+
+    let mut hash = [0u8, ..3];
+    assert_eq!(Ok(()), base16::decode_exact(hash, bytes!("666f6f")));
+    assert_eq!(bytes!("foo"), hash.as_slice());
+
+    let mut key = [0u8, ..6];
+    assert_eq!(Ok(()), base64::Standard.decode_exact(key, bytes!("Zm9vYmFy")));
+    assert_eq!(bytes!("foobar"), key.as_slice());
+
+    let mut text = [0u8, ..7];
+    assert_eq!(Ok(()), base45::Standard.decode_exact(text, bytes!("%69 VD92EX0")));
+    assert_eq!(bytes!("Hello!!"), text.as_slice());
+
+    let mut key = [0u8, ..4];
+    assert_eq!(Err(~"expected 4 decoded bytes, got 5"),
+               base64::Standard.decode_exact(key, bytes!("Zm9vYmE=")));
+}
+
+#[test]
+fn test_encode_to_str() {
+    let mut buf = [0u8, ..16];
+    assert_eq!("Zm9vYmE=", base64::Standard.encode_to_str(buf, bytes!("fooba")));
+    assert_eq!("MZXW6YTBOI======", base32::Standard.encode_to_str(buf, bytes!("foobar")));
+    assert_eq!("666F6F", base16::encode_to_str(buf, bytes!("foo")));
+}
+
+#[test]
+fn test_to_base() {
+    let source = bytes!("foobar");
+
+    assert_eq!(~"Zm9vYmFy", source.to_base64(base64::Standard));
+    assert_eq!(~"CPNMUOJ1E8======", source.to_base32(base32::Hex));
+    assert_eq!(~"666F6F626172", source.to_owned().to_base16());
+}
+
+#[test]
+fn test_from_base() {
+    assert_eq!(Ok(bytes!("fo?ba?").to_owned()), "Zm8_YmE_".from_base64(base64::UrlSafe));
+    assert_eq!(Ok(bytes!("foobar").to_owned()), (~"MZXW6YTBOI======").from_base32(base32::Standard));
+    assert_eq!(Ok(bytes!("foo").to_owned()), "666f6f".from_base16());
+    assert!("666".from_base16().is_err());
+}
+
+#[deriving(Eq)]
+struct Token([u8, ..3]);
+
+impl FromDecoded for Token {
+    fn from_decoded(bytes: ~[u8]) -> Result<Token, ~str> {
+        if bytes.len() != 3 {
+            return Err(~"a token is 3 bytes long");
+        }
+        Ok(Token([bytes[0], bytes[1], bytes[2]]))
+    }
+}
+
+#[test]
+fn test_from_base_into() {
+    assert_eq!(Ok(Token([0x66, 0x6f, 0x6f])), "Zm9v".from_base64_into::<Token>(base64::Standard));
+    assert_eq!(Ok(Token([0x66, 0x6f, 0x6f])), "666F6F".from_base16_into::<Token>());
+    assert!("Zm9vYg==".from_base64_into::<Token>(base64::Standard).is_err());
+}
+
+#[test]
+fn test_encoded_string() {
+    let text = EncodedString::new(base64::UrlSafe, ~"Zm8_YmE_").unwrap();
+
+    assert_eq!(6, text.decoded_len());
+    assert_eq!("Zm8_YmE_", text.as_str());
+    assert_eq!(bytes!("fo?ba?"), text.decode().as_slice());
+    assert_eq!(6, text.as_encoded_str().decoded_len());
+
+    assert!(EncodedString::new(base64::Standard, ~"Zm8_YmE_").is_err());
+    assert!(EncodedString::new(base32::Standard, ~"MZXW6YQ").is_err());
+}
+
+#[test]
+fn test_encoded_str() {
+    let text: Base16Str = EncodedStr::new(base16::Standard, "666f6f").unwrap();
+
+    assert_eq!(3, text.decoded_len());
+    assert_eq!(bytes!("foo"), text.decode().as_slice());
+    assert_eq!(~"666f6f", text.to_encoded_string().into_str());
+
+    assert!(EncodedStr::new(base16::Standard, "666").is_err());
+}
+
+#[test]
+fn test_encoded_string_variants() {
+    fn url_decoded_len(text: &Base64UrlString) -> uint { text.decoded_len() }
+
+    let text: Base64UrlString = EncodedString::new(validated::Base64UrlSafe, ~"Zm8_YmE_").unwrap();
+    assert_eq!(6, url_decoded_len(&text));
+    assert_eq!(bytes!("fo?ba?"), text.decode().as_slice());
+
+    let text: Base64String = EncodedString::new(validated::Base64Standard, ~"Zm8/YmE/").unwrap();
+    assert_eq!(bytes!("fo?ba?"), text.decode().as_slice());
+
+    let text: Base64UrlStr = EncodedStr::new(validated::Base64UrlSafe, "Zm8=").unwrap();
+    assert_eq!(2, text.decoded_len());
+    assert!(EncodedStr::new(validated::Base64UrlSafe, "Zm8/YmE/").is_err());
+    assert!(EncodedStr::new(validated::Base32Standard, "MZXW6YQ=").is_ok());
+}
+
+#[test]
+fn test_encode_iter() {
+    let source = bytes!("foobar");
+
+    let actual: ~[u8] = iter::encode(base64::Standard, source.iter().map(|&b| b)).collect();
+    assert_eq!(bytes!("Zm9vYmFy"), actual.as_slice());
+
+    let actual: ~[u8] = iter::encode(base32::Standard, source.iter().map(|&b| b)).collect();
+    assert_eq!(bytes!("MZXW6YTBOI======"), actual.as_slice());
+
+    let actual: ~[u8] = iter::encode(base16::Standard, source.slice_to(2).iter().map(|&b| b)).collect();
+    assert_eq!(bytes!("666F"), actual.as_slice());
+}
+
+#[test]
+fn test_decode_iter() {
+    let source = bytes!("Zm9vYmE=");
+    let actual: ~[Result<u8, ~str>] = iter::decode(base64::Standard, source.iter().map(|&b| b)).collect();
+    let expect: ~[Result<u8, ~str>] = bytes!("fooba").iter().map(|&b| Ok(b)).collect();
+    assert_eq!(expect, actual);
+
+    let source = bytes!("Zg==Zg==");
+    let mut it = iter::decode(base64::Standard, source.iter().map(|&b| b));
+    assert_eq!(Some(Ok(0x66)), it.next());
+    assert!(it.next().unwrap().is_err());
+    assert_eq!(None, it.next());
+
+    let source = bytes!("66z6");
+    let mut it = iter::decode(base16::Standard, source.iter().map(|&b| b));
+    assert_eq!(Some(Ok(0x66)), it.next());
+    assert!(it.next().unwrap().is_err());
+}
+
+#[test]
+fn test_base32_crockford_encode() {
+    let source = ~[~"", ~"f", ~"fo", ~"foo", ~"foob", ~"fooba", ~"foobar"];
+    let expect = ~[~"", ~"CR", ~"CSQG", ~"CSQPY", ~"CSQPYRG", ~"CSQPYRK1", ~"CSQPYRK1E8"];
+
+    do t(source, expect) |src| {
+        base32::Crockford.encode(src)
+    }
+}
+
+#[test]
+fn test_base32_crockford_decode() {
+    let source = ~[~"", ~"CR", ~"csqg", ~"CSQPY", ~"CSQP-YRG", ~"CSQPYRKI", ~"csqp-yrkl-e8"];
+    let expect = ~[~"", ~"f", ~"fo", ~"foo", ~"foob", ~"fooba", ~"foobar"];
+
+    do t(source, expect) |src| {
+        base32::Crockford.decode(src)
+    }
+
+    assert!(base32::Crockford.decode_result(bytes!("CSQ")).is_err());
+    assert!(base32::Crockford.decode_result(bytes!("CSQU")).is_err());
+    assert!(base32::Crockford.decode_result(bytes!("CSQG====")).is_err());
+
+    // error positions count the skipped hyphens
+    assert_eq!(Err(~"illegal base32 data at input byte 3"),
+               base32::Crockford.decode_result(bytes!("CS-U")));
+    assert_eq!(Err(~"illegal base32 data at input byte 9"),
+               base32::Crockford.decode_result(bytes!("CSQP-YRK1E-")));
+}
+
+#[test]
+fn test_base32_crockford_check() {
+    assert_eq!('6' as u8, base32::crockford_check_symbol(bytes!("foobar")));
+    assert_eq!('U' as u8, base32::crockford_check_symbol(bytes!("fooba")));
+    assert_eq!(bytes!("CSQPYRK1E86"), base32::crockford_encode_check(bytes!("foobar")).as_slice());
+
+    assert_eq!(Ok(bytes!("fooba").to_owned()), base32::crockford_decode_check(bytes!("csqpyrk1u")));
+    assert_eq!(Err(~"check symbol mismatch: expected 6, found 7"),
+               base32::crockford_decode_check(bytes!("CSQPYRK1E87")));
+}
+
+#[test]
+fn test_base32_unpadded_short_quantum() {
+    let mut dst = [0u8, ..1];
+    assert_eq!(Ok(1), base32::Crockford.decode_to(dst, bytes!("CR")));
+    assert_eq!(0x66, dst[0]);
+}
+
+#[test]
+fn test_base32_crockford_hyphens() {
+    let mut id = [0u8, ..6];
+    assert_eq!(Ok(()), base32::Crockford.decode_exact(id, bytes!("CSQP-YRK1-E8")));
+    assert_eq!(bytes!("foobar"), id.as_slice());
+
+    let source = bytes!("CSQP-YRK1-E8");
+    let actual: ~[Result<u8, ~str>] = iter::decode(base32::Crockford, source.iter().map(|&b| b)).collect();
+    let expect: ~[Result<u8, ~str>] = bytes!("foobar").iter().map(|&b| Ok(b)).collect();
+    assert_eq!(expect, actual);
+
+    let data = vec::from_fn(3000, |i| (i * 7) as u8);
+    let encoded = base32::Crockford.encode(data);
+    let mut source = ~[];
+    for (i, chunk) in encoded.chunk_iter(4).enumerate() {
+        if i > 0 { source.push('-' as u8); }
+        source.push_all(chunk);
+    }
+    assert!(source.len() > 4096);
+    let mut reader = MemReader::new(source);
+    let mut writer = MemWriter::new();
+    let n = base32::Crockford.decode_stream(&mut reader as &mut Reader, &mut writer as &mut Writer);
+    assert_eq!(Ok(3000), n);
+    assert_eq!(data.as_slice(), writer.inner().as_slice());
+}
+
+#[test]
+fn test_zbase32() {
+    assert_eq!(bytes!("c3zs6aubqe"), base32::ZBase32.encode(bytes!("foobar")).as_slice());
+    assert_eq!(bytes!("foobar"), base32::ZBase32.decode(bytes!("c3zs6aubqe")).as_slice());
+    assert!(base32::ZBase32.decode_result(bytes!("C3ZS6AUBQE")).is_err());
+    assert!(base32::ZBase32.is_valid(bytes!("c3zs6aubqe")));
+    assert_eq!(Some(encoding::ZBase32), from_str::<Encoding>("z-base-32"));
+}
+
+#[test]
+fn test_zbase32_bits() {
+    assert_eq!(bytes!("y"), base32::zbase32_encode_bits([0x00], 1).as_slice());
+    assert_eq!(bytes!("o"), base32::zbase32_encode_bits([0x80], 1).as_slice());
+    assert_eq!(bytes!("a"), base32::zbase32_encode_bits([0xc0], 2).as_slice());
+    assert_eq!(bytes!("on"), base32::zbase32_encode_bits([0x80, 0x80], 10).as_slice());
+    assert_eq!(bytes!("tqre"), base32::zbase32_encode_bits([0x8b, 0x88, 0x80], 20).as_slice());
+    assert_eq!(bytes!("6im5sd"), base32::zbase32_encode_bits([0xf5, 0x57, 0xbb, 0x0c], 30).as_slice());
+
+    assert_eq!(Ok(~[0x80]), base32::zbase32_decode_bits(bytes!("o"), 1));
+    assert_eq!(Ok(~[0x80, 0x80]), base32::zbase32_decode_bits(bytes!("on"), 10));
+    assert_eq!(Ok(~[0xf5, 0x57, 0xbb, 0x0c]), base32::zbase32_decode_bits(bytes!("6im5sd"), 30));
+    assert!(base32::zbase32_decode_bits(bytes!("on"), 11).is_err());
+}
+
+#[test]
+fn test_base58() {
+    let source = ~[~"", ~"Hello World!", ~"foobar"];
+    let expect = ~[~"", ~"2NEpo7TZRRrLZSi2U", ~"t1Zv2yaZ"];
+
+    do t(source, expect) |src| {
+        base58::Bitcoin.encode(src)
+    }
+
+    assert_eq!(bytes!("11233QC4"), base58::Bitcoin.encode([0, 0, 0x28, 0x7f, 0xb4, 0xcd]).as_slice());
+    assert_eq!(bytes!("11233pc4"), base58::Flickr.encode([0, 0, 0x28, 0x7f, 0xb4, 0xcd]).as_slice());
+    assert_eq!(Ok(~[0, 0, 0x28, 0x7f, 0xb4, 0xcd]), base58::Bitcoin.decode_result(bytes!("11233QC4")));
+    assert_eq!(bytes!("foobar"), base58::Flickr.decode(bytes!("T1yV2Yzy")).as_slice());
+    assert_eq!(Err(~"illegal base58 data at input byte 2"), base58::Bitcoin.decode_result(bytes!("t10v")));
+}
+
+#[test]
+fn test_base58_check() {
+    let zeros = [0u8, ..20];
+    assert_eq!(bytes!("1111111111111111111114oLvT2"), base58::Bitcoin.encode_check(0, zeros).as_slice());
+    assert_eq!(Ok((0, zeros.to_owned())), base58::Bitcoin.decode_check(bytes!("1111111111111111111114oLvT2")));
+    assert_eq!(Ok((0x80, bytes!("foobar").to_owned())), base58::Bitcoin.decode_check(bytes!("YqioaBEnwRvkh6h")));
+
+    assert_eq!(Err(base58::ChecksumMismatch(0x94a00911, 0x94a00912)),
+               base58::Bitcoin.decode_check(bytes!("1111111111111111111114oLvT3")));
+    assert_eq!(Err(base58::TooShort(2)), base58::Bitcoin.decode_check(bytes!("11")));
+    assert!(base58::Bitcoin.decode_check(bytes!("0OIl")).is_err());
+}
+
+#[test]
+fn test_base85_ascii85() {
+    let source = ~[~"", ~"Man is", ~"foobar"];
+    let expect = ~[~"<~~>", ~"<~9jqo^Bla~>", ~"<~AoDTs@<)~>"];
+
+    do t(source, expect) |src| {
+        base85::Ascii85.encode(src)
+    }
+
+    assert_eq!(bytes!("<~z@:E^~>"), base85::Ascii85.encode(bytes!("\x00\x00\x00\x00abc")).as_slice());
+    assert_eq!(bytes!("\x00\x00\x00\x00abc"), base85::Ascii85.decode(bytes!(" <~z@:\nE^~>\n")).as_slice());
+    assert_eq!(bytes!("foobar"), base85::Ascii85.decode(bytes!("AoDTs @<)~>")).as_slice());
+    assert!(base85::Ascii85.decode_result(bytes!("<~AoDTs")).is_err());
+    assert!(base85::Ascii85.decode_result(bytes!("<~Ao z~>")).is_err());
+    assert!(base85::Ascii85.decode_result(bytes!("<~AoDTsA~>")).is_err());
+}
+
+#[test]
+fn test_base85_z85_git() {
+    let key = [0x86u8, 0x4f, 0xd2, 0x6f, 0xb5, 0x59, 0xf7, 0x5b];
+    assert_eq!(bytes!("HelloWorld"), base85::Z85.encode(key).as_slice());
+    assert_eq!(key.to_owned(), base85::Z85.decode(bytes!("HelloWorld")));
+    assert!(base85::Z85.decode_result(bytes!("Hello")).is_ok());
+    assert!(base85::Z85.decode_result(bytes!("Hell")).is_err());
+    assert!(base85::Z85.decode_result(bytes!("Hello World")).is_err());
+    assert_eq!(Ok(bytes!("HelloWorld").to_owned()), base85::Z85.encode_result(key));
+    assert_eq!(Err(~"z85 data length 3 is not a multiple of 4"), base85::Z85.encode_result(bytes!("foo")));
+
+    // the zlib data of two literal hunks from `git diff --binary`
+    let hunk = bytes!("zcmV~$(G7qg3<W^cMcx7q^%`ix2UKZ^;`H9BnQ$cGm+m=VuxGfnf@Y&YYUfzk_5+zg\n",
+                      "RgyEt)v}&v~9)qkXo_<%O7xMrB\n",
+                      "\n");
+    let deflated = base85::Git.decode(hunk);
+    assert_eq!(70, deflated.len());
+    assert_eq!(hunk.slice_to(hunk.len() - 1), base85::Git.encode(deflated).as_slice());
+
+    let deflated = bytes!(0x78, 0x01, 0x4b, 0xcb, 0xcf, 0x4f, 0x4a, 0x2c, 0x62, 0x00, 0x00, 0x0b, 0x25, 0x02, 0x7a);
+    assert_eq!(bytes!("OcmYex&reD$VgLXOB?5W?\n"), base85::Git.encode(deflated).as_slice());
+    assert_eq!(deflated.to_owned(), base85::Git.decode(bytes!("OcmYex&reD$VgLXOB?5W?\n\n")));
+
+    assert_eq!(Err(~"illegal base85 line length 0 at input byte 0"),
+               base85::Git.decode_result(bytes!("0cmYex\n")));
+    assert_eq!(Err(~"base85 line at input byte 0 should have 21 characters, got 20"),
+               base85::Git.decode_result(bytes!("OcmYex&reD$VgLXOB?5W\n")));
+    assert_eq!(Err(~"illegal base85 data at input byte 3"), base85::Git.decode_result(bytes!("Dcm\"ex")));
+}
+
+#[test]
+fn test_base85_encoding() {
+    let source = bytes!("\x86\x4f\xd2\x6f\xb5\x59\xf7\x5b");
+    for name in [~"ascii85", ~"z85", ~"base85git"].iter() {
+        let encoding: Encoding = from_str(name.as_slice()).unwrap();
+        assert_eq!(name.clone(), encoding.to_str());
+        assert!(encoding.codec().is_none());
+        let text = encoding.encode(source);
+        assert_eq!(Ok(source.to_owned()), encoding.decode_result(text));
+        assert_eq!(Ok(source.len()), encoding.validate(text));
+    }
+
+    assert_eq!(~"HelloWorld", encoding::Z85.encode_to_string(source));
+    assert!(encoding::Z85.encode_result(bytes!("foo")).is_err());
+    assert_eq!(Ok(bytes!("<~AoDTs~>").to_owned()), encoding::Ascii85.encode_result(bytes!("foob")));
+}
+
+#[test]
+#[should_fail]
+fn test_base85_z85_encode_length() {
+    base85::Z85.encode(bytes!("foo"));
+}
+
+#[test]
+fn test_base45() {
+    let source = ~[~"", ~"AB", ~"Hello!!", ~"base-45", ~"ietf!"];
+    let expect = ~[~"", ~"BB8", ~"%69 VD92EX0", ~"UJCLQE7W581", ~"QED8WEX0"];
+
+    do t(source.clone(), expect.clone()) |src| {
+        base45::encode(src)
+    }
+    do t(expect, source) |src| {
+        base45::decode(src)
+    }
+}
+
+#[test]
+fn test_base45_invalid() {
+    assert_eq!(Err(~"base45 triplet out of range at input byte 0"), base45::decode_result(bytes!("GGW")));
+    assert_eq!(Err(~"base45 pair out of range at input byte 3"), base45::decode_result(bytes!("BB8ZZ")));
+    assert_eq!(Err(~"invalid base45 length 4"), base45::decode_result(bytes!("BB8B")));
+    assert_eq!(Err(~"illegal base45 data at input byte 1"), base45::decode_result(bytes!("Bb8")));
+    assert_eq!(Ok(7), base45::validate(bytes!("%69 VD92EX0")));
+
+    // the complete groups before a lone trailing character are recovered
+    assert_eq!(Err((bytes!("AB").to_owned(), ~"invalid base45 length 4")),
+               base45::Standard.decode_partial(bytes!("BB8%")));
+}
+
+#[test]
+fn test_base45_stream() {
+    let data = vec::from_fn(3001, |i| (i * 7) as u8);
+    let encoded = base45::encode(data);
+    assert!(encoded.len() > 4096);
+
+    let mut reader = MemReader::new(data.clone());
+    let mut writer = MemWriter::new();
+    base45::Standard.encode_stream(&mut reader as &mut Reader, &mut writer as &mut Writer);
+    assert_eq!(encoded.as_slice(), writer.inner().as_slice());
+
+    let mut reader = MemReader::new(encoded.clone());
+    let mut writer = MemWriter::new();
+    let n = base45::Standard.decode_stream(&mut reader as &mut Reader, &mut writer as &mut Writer);
+    assert_eq!(Ok(3001), n);
+    assert_eq!(data.as_slice(), writer.inner().as_slice());
+}
+
+#[test]
+fn test_base45_encoding() {
+    let encoding: Encoding = from_str("base45").unwrap();
+    assert_eq!(encoding::Base45, encoding);
+    assert_eq!(~"base45", encoding.to_str());
+    assert_eq!(bytes!("%69 VD92EX0"), encoding.encode(bytes!("Hello!!")).as_slice());
+    assert_eq!(bytes!("Hello!!"), encoding.decode(bytes!("%69 VD92EX0")).as_slice());
+}
+
+#[test]
+fn test_base58_encoding() {
+    let source = bytes!("\x00\x00hello world");
+    for name in [~"base58", ~"base58flickr"].iter() {
+        let encoding: Encoding = from_str(name.as_slice()).unwrap();
+        assert_eq!(name.clone(), encoding.to_str());
+        assert!(encoding.codec().is_none());
+        let text = encoding.encode_to_string(source);
+        assert_eq!(Ok(source.to_owned()), encoding.decode_str_result(text.as_slice()));
+        assert_eq!(Ok(source.len()), encoding.validate(text.as_bytes()));
+    }
+
+    assert_eq!(~"11StV1DL6CwTryKyV", encoding::Base58Bitcoin.encode_to_string(source));
+    assert_eq!(~"11rTu1dk6cWsRYjYu", encoding::Base58Flickr.encode_to_string(source));
+    assert!(!encoding::Base58Bitcoin.is_valid(bytes!("0OIl")));
+    assert!(encoding::Base45.codec().is_some());
+}
+
+#[test]
+fn test_base32_quintets() {
+    assert_eq!(~[12, 25, 23, 22, 30, 24, 19, 1, 14, 8], base32::to_quintets(bytes!("foobar")));
+    assert_eq!(Ok(bytes!("foobar").to_owned()), base32::from_quintets([12, 25, 23, 22, 30, 24, 19, 1, 14, 8]));
+    assert!(base32::from_quintets([12, 25, 23, 22, 30, 24, 19, 1, 14, 9]).is_err());
+    assert!(base32::from_quintets([12, 25, 23]).is_err());
+    assert!(base32::from_quintets([12, 32]).is_err());
+}
+
+#[test]
+fn test_bech32() {
+    assert_eq!(Ok(~"a12uel5l"), bech32::Bech32.encode("A", []));
+    assert_eq!(Ok(~"a1lqfn3a"), bech32::Bech32m.encode("a", []));
+    assert_eq!(Ok(~"test1vehk7cnpwgpmsvw5"), bech32::Bech32.encode("test", bytes!("foobar")));
+    assert_eq!(Ok(~"test1vehk7cnpwg58qqtk"), bech32::Bech32m.encode("test", bytes!("foobar")));
+
+    assert_eq!(Ok((~"a", ~[])), bech32::Bech32.decode("A12UEL5L"));
+    assert_eq!(Ok((~"test", bytes!("foobar").to_owned())), bech32::Bech32m.decode("test1vehk7cnpwg58qqtk"));
+
+    let values: ~[u8] = range(0u8, 32).collect();
+    assert_eq!(Ok((~"abcdef", values)),
+               bech32::Bech32.decode_quintets("abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw"));
+}
+
+#[test]
+fn test_bech32_invalid() {
+    assert_eq!(Err(bech32::MixedCase), bech32::Bech32.decode("A12uEL5L"));
+    assert_eq!(Err(bech32::MissingSeparator), bech32::Bech32.decode("pzry9x0s0muk"));
+    assert_eq!(Err(bech32::InvalidHrp(0)), bech32::Bech32.decode("1pzry9x0s0muk"));
+    assert_eq!(Err(bech32::InvalidChar(6)), bech32::Bech32.decode("test1vbhk7cnpwgpmsvw5"));
+    assert_eq!(Err(bech32::InvalidChecksum(Some(8))), bech32::Bech32.decode("test1vehx7cnpwgpmsvw5"));
+    assert_eq!(Err(bech32::InvalidChecksum(None)), bech32::Bech32m.decode("test1vehk7cnpwgpmsvw5"));
+    assert_eq!(Err(bech32::InvalidLength(92)), bech32::Bech32.encode("a", [0u8, ..52]));
+}
+
+#[test]
+fn test_base64_bcrypt() {
+    let source = ~[~"", ~"f", ~"fo", ~"foo", ~"foobar!"];
+    let expect = ~[~"", ~"Xe", ~"Xk6", ~"Xk7t", ~"Xk7tWkDwGO"];
+
+    do t(source.clone(), expect.clone()) |src| {
+        base64::Bcrypt.encode(src)
+    }
+    do t(expect, source) |src| {
+        base64::Bcrypt.decode(src)
+    }
+
+    assert!(base64::Bcrypt.decode_result(bytes!("Xk7tW")).is_err());
+    assert!(base64::Bcrypt.decode_result(bytes!("Xk7=")).is_err());
+    assert_eq!(Some(encoding::Base64Bcrypt), from_str::<Encoding>("bcrypt"));
+}
+
+#[test]
+fn test_base64_crypt() {
+    assert_eq!(bytes!("axqPW3aQV."), base64::Crypt.encode(bytes!("foobar!")).as_slice());
+    assert_eq!(bytes!("foobar!"), base64::Crypt.decode(bytes!("axqPW3aQV.")).as_slice());
+
+    let digest = [0x6du8, 0x00, 0xc2, 0x57, 0x65, 0x7c, 0x5d, 0xd2,
+                  0xad, 0xcc, 0xae, 0xc5, 0xde, 0x79, 0xcb, 0xb5];
+    assert_eq!(bytes!("SrJPt7B.9rekpmwJwtON31"), base64::md5_crypt_encode(digest).as_slice());
+    assert_eq!(Ok(digest.to_owned()), base64::md5_crypt_decode(bytes!("SrJPt7B.9rekpmwJwtON31")));
+    assert!(base64::md5_crypt_decode(bytes!("SrJPt7B.9rekpmwJwtON")).is_err());
+
+    let digest: ~[u8] = range(0u8, 64).collect();
+    let hash = base64::sha512_crypt_encode(digest);
+    assert_eq!(86, hash.len());
+    assert_eq!(Ok(digest), base64::sha512_crypt_decode(hash));
+    let hash = base64::sha256_crypt_encode(digest.slice_to(32));
+    assert_eq!(43, hash.len());
+    assert_eq!(Ok(digest.slice_to(32).to_owned()), base64::sha256_crypt_decode(hash));
+}
+
+#[test]
+fn test_imap_utf7() {
+    let source = ~[~"INBOX", ~"~peter/mail/台北/日本語", ~"A&B", ~"\U0001F600x", ~"é"];
+    let expect = ~[~"INBOX", ~"~peter/mail/&U,BTFw-/&ZeVnLIqe-", ~"A&-B", ~"&2D3eAA-x", ~"&AOk-"];
+
+    for (s, e) in source.iter().zip(expect.iter()) {
+        assert_eq!(*e, base64::imap_utf7_encode(s.as_slice()));
+        assert_eq!(Ok(s.clone()), base64::imap_utf7_decode(e.as_slice()));
+    }
+
+    assert!(base64::imap_utf7_decode("&U,BTFw").is_err());
+    assert!(base64::imap_utf7_decode("&2D0-").is_err());
+    assert!(base64::imap_utf7_decode("&U/BTFw-").is_err());
+    assert!(base64::imap_utf7_decode("tab\there").is_err());
+
+    // only the canonical encoding is accepted
+    assert!(base64::imap_utf7_decode("&AGE-").is_err());
+    assert!(base64::imap_utf7_decode("&AOk-&AOk-").is_err());
+    assert!(base64::imap_utf7_decode("&AOl-").is_err());
+    assert!(base64::imap_utf7_decode("&U,BTFx-").is_err());
+    assert_eq!(Ok(~"éé"), base64::imap_utf7_decode("&AOkA6Q-"));
+    assert_eq!(Ok(~"é&é"), base64::imap_utf7_decode("&AOk-&-&AOk-"));
+}
+
+#[test]
+fn test_uuencode() {
+    assert_eq!(bytes!("&9F]O8F%R\n`\n"), uuencode::Uuencode.encode(bytes!("foobar")).as_slice());
+    assert_eq!(bytes!("4NaxjMa3m\n+\n"), uuencode::Xxencode.encode(bytes!("foobar")).as_slice());
+    assert_eq!(bytes!("foobar"), uuencode::Uuencode.decode(bytes!("&9F]O8F%R\r\n`\r\n")).as_slice());
+    assert_eq!(bytes!("foobar"), uuencode::Xxencode.decode(bytes!("4NaxjMa3m\n+\n")).as_slice());
+
+    let data = vec::from_elem(100, 'a' as u8);
+    let encoded = uuencode::Uuencode.encode(data);
+    assert_eq!(bytes!("M86%A"), encoded.slice_to(5));
+    assert_eq!(data, uuencode::Uuencode.decode(encoded));
+
+    assert!(uuencode::Uuencode.decode_result(bytes!("&9F]O8F\n")).is_err());
+    assert!(uuencode::Xxencode.decode_result(bytes!("4Na=jMa3m\n")).is_err());
+}
+
+#[test]
+fn test_uuencode_files() {
+    let encoded = uuencode::Uuencode.encode_file(420, "cat.txt", bytes!("Cat"));
+    assert_eq!(bytes!("begin 644 cat.txt\n#0V%T\n`\nend\n"), encoded.as_slice());
+
+    let archive = bytes!("From: someone\n\nbegin 644 cat.txt\n#0V%T\n`\nend\n",
+                         "--\nbegin 600 foo bar\n&9F]O8F%R\n`\nend\n");
+    let files: ~[Result<uuencode::UuFile, ~str>] = uuencode::Uuencode.files(archive).collect();
+    assert_eq!(2, files.len());
+    assert_eq!(Ok(uuencode::UuFile { mode: 420, name: ~"cat.txt", data: bytes!("Cat").to_owned() }), files[0].clone());
+    assert_eq!(Ok(uuencode::UuFile { mode: 384, name: ~"foo bar", data: bytes!("foobar").to_owned() }), files[1].clone());
+
+    assert!(uuencode::Uuencode.decode_file(bytes!("begin 644 cat.txt\n#0V%T\n`\n")).is_err());
+    assert!(uuencode::Uuencode.decode_file(bytes!("no files here\n")).is_err());
+}
+
+#[test]
+fn test_uuencode_read_files() {
+    let data = vec::from_fn(10000, |i| (i * 7) as u8);
+    let mut archive = bytes!("From: someone\r\n\r\n").to_owned();
+    archive.push_all(uuencode::Uuencode.encode_file(420, "data.bin", data));
+    archive.push_all(bytes!("--\n"));
+    archive.push_all(uuencode::Uuencode.encode_file(384, "cat.txt", bytes!("Cat")));
+    assert!(archive.len() > 4096);
+
+    let files: ~[Result<uuencode::UuFile, ~str>] =
+        uuencode::Uuencode.read_files(MemReader::new(archive.clone())).collect();
+    assert_eq!(2, files.len());
+    assert_eq!(Ok(uuencode::UuFile { mode: 420, name: ~"data.bin", data: data.clone() }), files[0].clone());
+    assert_eq!(Ok(uuencode::UuFile { mode: 384, name: ~"cat.txt", data: bytes!("Cat").to_owned() }), files[1].clone());
+
+    let expect: ~[Result<uuencode::UuFile, ~str>] = uuencode::Uuencode.files(archive).collect();
+    assert_eq!(expect, files);
+
+    let archive = bytes!("begin 644 cat.txt\n#0V%T\n`\n").to_owned();
+    let mut it = uuencode::Uuencode.read_files(MemReader::new(archive));
+    assert_eq!(Some(Err(~"missing end line for cat.txt")), it.next());
+    assert_eq!(None, it.next());
+}
+
+#[test]
+fn test_uuencode_writer() {
+    let data = vec::from_elem(50, 'a' as u8);
+    let mut w = UuWriter::new(uuencode::Uuencode, MemWriter::new());
+    w.begin(420, "a.txt");
+    w.write(data.slice_to(20));
+    w.write(data.slice_from(20));
+    w.end();
+    w.begin(420, "cat.txt");
+    w.write(bytes!("Cat"));
+    w.end();
+
+    let mut expect = uuencode::Uuencode.encode_file(420, "a.txt", data);
+    expect.push_all(uuencode::Uuencode.encode_file(420, "cat.txt", bytes!("Cat")));
+    assert_eq!(expect, w.inner().inner());
+}
+
+#[test]
+fn test_quoted_printable_encode() {
+    let qp = quoted_printable::Text;
+    assert_eq!(bytes!("Hello, World!"), quoted_printable::encode(bytes!("Hello, World!"), qp).as_slice());
+    assert_eq!(bytes!("a=3Db caf=C3=A9"), quoted_printable::encode(bytes!("a=b caf", 0xc3, 0xa9), qp).as_slice());
+    assert_eq!(bytes!("trailing=20\r\nline=09"), quoted_printable::encode(bytes!("trailing \nline\t"), qp).as_slice());
+    assert_eq!(bytes!("a=0D=0Ab"), quoted_printable::encode(bytes!("a\r\nb"), quoted_printable::Binary).as_slice());
+
+    let line = vec::from_elem(76, 'a' as u8);
+    assert_eq!(line.clone(), quoted_printable::encode(line, qp));
+    let long = vec::from_elem(100, 'a' as u8);
+    let encoded = quoted_printable::encode(long, qp);
+    assert_eq!(bytes!("=\r\n"), encoded.slice(75, 78));
+    assert_eq!(103, encoded.len());
+    assert_eq!(Ok(long.clone()), quoted_printable::decode_result(encoded));
+
+    let escapes = vec::from_elem(30, 0xffu8);
+    let encoded = quoted_printable::encode(escapes, qp);
+    assert_eq!(bytes!("=FF=\r\n=FF"), encoded.slice(72, 81));
+}
+
+#[test]
+fn test_quoted_printable_decode() {
+    assert_eq!(bytes!("caf", 0xc3, 0xa9, "\r\nxy=ZZ"),
+               quoted_printable::decode(bytes!("caf=c3=a9 \r\nx= \r\ny=ZZ")).as_slice());
+    assert_eq!(Ok(bytes!("caf", 0xc3, 0xa9, "\r\nxy").to_owned()),
+               quoted_printable::decode_result(bytes!("caf=C3=A9\r\nx=\r\ny")));
+
+    assert_eq!(Err(~"illegal quoted-printable data at input byte 3"),
+               quoted_printable::decode_result(bytes!("caf=c3")));
+    assert_eq!(Err(~"trailing whitespace at input byte 1"), quoted_printable::decode_result(bytes!("x \r\n")));
+    assert_eq!(Err(~"illegal quoted-printable data at input byte 1"), quoted_printable::decode_result(bytes!("y=ZZ")));
+    assert!(quoted_printable::decode_result(vec::from_elem(77, 'a' as u8)).is_err());
+    assert!(quoted_printable::decode_result(bytes!("a\rb")).is_err());
+}
+
+// Built by `make test-serialize`.
+#[cfg(serialize)]
+mod serialize {
+    use std::io;
+    use extra::json;
+    use extra::serialize::{Encodable, Decodable};
+
+    use rfc4648::serialize::{Base16Bytes, Base32Bytes, Base32UnpaddedBytes, Base32HexBytes,
+                             Base32HexUnpaddedBytes, Base32CrockfordBytes, ZBase32Bytes,
+                             Base45Bytes, Base64Bytes, Base64UnpaddedBytes, Base64UrlBytes,
+                             Base64UrlUnpaddedBytes, Base64BcryptBytes, Base64CryptBytes,
+                             Base16Array20, Base64Array16, Base64UrlUnpaddedArray32};
+
+    #[deriving(Eq, Encodable, Decodable)]
+    struct Record {
+        key: Base64UrlUnpaddedBytes,
+        digest: Option<Base16Bytes>,
+        id: Base32CrockfordBytes,
+        tag: ZBase32Bytes,
+        salt: Base64BcryptBytes,
+        hash: Base64CryptBytes,
+    }
+
+    #[deriving(Eq, Encodable, Decodable)]
+    struct Padded {
+        a: Base32Bytes,
+        b: Base32HexBytes,
+        c: Base64Bytes,
+        d: Base64UrlBytes,
+        e: Base64UnpaddedBytes,
+        f: Base32UnpaddedBytes,
+        g: Base32HexUnpaddedBytes,
+        h: Base45Bytes,
+    }
+
+    #[deriving(Eq, Encodable, Decodable)]
+    struct Keys {
+        nonce: Base64Array16,
+        digest: Option<Base16Array20>,
+        key: Base64UrlUnpaddedArray32,
+    }
+
+    fn to_json<T: Encodable<json::Encoder>>(value: &T) -> ~str {
+        do io::with_str_writer |wr| {
+            let mut encoder = json::Encoder(wr);
+            value.encode(&mut encoder);
+        }
+    }
+
+    fn from_json<T: Decodable<json::Decoder>>(text: &str) -> T {
+        let mut decoder = json::Decoder(json::from_str(text).unwrap());
+        Decodable::decode(&mut decoder)
+    }
+
+    #[test]
+    fn test_serialize_json() {
+        let record = Record {
+            key: Base64UrlUnpaddedBytes(bytes!("fooba").to_owned()),
+            digest: None,
+            id: Base32CrockfordBytes(bytes!("foobar").to_owned()),
+            tag: ZBase32Bytes(bytes!("foobar").to_owned()),
+            salt: Base64BcryptBytes(bytes!("foobar!").to_owned()),
+            hash: Base64CryptBytes(bytes!("foobar!").to_owned()),
+        };
+        let text = to_json(&record);
+        assert_eq!(~"{\"key\":\"Zm9vYmE\",\"digest\":null,\"id\":\"CSQPYRK1E8\","
+                   + "\"tag\":\"c3zs6aubqe\",\"salt\":\"Xk7tWkDwGO\",\"hash\":\"axqPW3aQV.\"}", text);
+        let decoded: Record = from_json(text);
+        assert!(decoded == record);
+
+        let record = Record { digest: Some(Base16Bytes(bytes!("foo").to_owned())), .. record };
+        assert!(to_json(&record).contains("\"digest\":\"666F6F\""));
+        let decoded: Record = from_json(to_json(&record));
+        assert!(decoded == record);
+
+        let record: Record = from_json("{\"key\":\"Zm9vYmE\",\"digest\":\"666f6f\",\"id\":\"csqp-yrk1-e8\","
+                                       + "\"tag\":\"c3zs6aubqe\",\"salt\":\"Xk7tWkDwGO\",\"hash\":\"axqPW3aQV.\"}");
+        assert_eq!(Some(Base16Bytes(bytes!("foo").to_owned())), record.digest);
+        assert_eq!(Base32CrockfordBytes(bytes!("foobar").to_owned()), record.id);
+    }
+
+    #[test]
+    fn test_serialize_padding() {
+        let padded = Padded {
+            a: Base32Bytes(bytes!("f").to_owned()),
+            b: Base32HexBytes(bytes!("f").to_owned()),
+            c: Base64Bytes(bytes!("f").to_owned()),
+            d: Base64UrlBytes(bytes!("f").to_owned()),
+            e: Base64UnpaddedBytes(bytes!("f").to_owned()),
+            f: Base32UnpaddedBytes(bytes!("f").to_owned()),
+            g: Base32HexUnpaddedBytes(bytes!("f").to_owned()),
+            h: Base45Bytes(bytes!("AB").to_owned()),
+        };
+        let text = to_json(&padded);
+        assert_eq!(~"{\"a\":\"MY======\",\"b\":\"CO======\",\"c\":\"Zg==\",\"d\":\"Zg==\",\"e\":\"Zg\","
+                   + "\"f\":\"MY\",\"g\":\"CO\",\"h\":\"BB8\"}", text);
+        assert_eq!(padded, from_json(text));
+    }
+
+    #[test]
+    fn test_serialize_arrays() {
+        let keys = Keys {
+            nonce: Base64Array16([0x55u8, ..16]),
+            digest: Some(Base16Array20([0xabu8, ..20])),
+            key: Base64UrlUnpaddedArray32([0xffu8, ..32]),
+        };
+        let text = to_json(&keys);
+        assert_eq!(~"{\"nonce\":\"VVVVVVVVVVVVVVVVVVVVVQ==\","
+                   + "\"digest\":\"ABABABABABABABABABABABABABABABABABABABAB\","
+                   + "\"key\":\"__________________________________________8\"}", text);
+        let decoded: Keys = from_json(text);
+        assert!(decoded == keys);
+    }
+
+    #[test]
+    #[should_fail]
+    fn test_serialize_array_length() {
+        let _: Base64Array16 = from_json("\"Zm9vYmFy\"");
+    }
+
+    #[test]
+    #[should_fail]
+    fn test_serialize_unexpected_padding() {
+        let _: Base64UnpaddedBytes = from_json("\"Zg==\"");
+    }
+
+    #[test]
+    #[should_fail]
+    fn test_serialize_invalid() {
+        let _: Base64Bytes = from_json("\"Zm9v!\"");
+    }
+}
@@ -0,0 +1,33 @@
+// util.rs
+//
+// Helpers shared by the codec modules, kept out of the public API.
+
+use std::str;
+
+// The result of a decoding core such as `base64_decode`, carrying the
+// number of bytes decoded so far.
+//
+// The cores share two conventions. With `validate_only`, `dst` is ignored
+// and `src` is only checked. Without `src`, `dst` holds the input and is
+// decoded over itself: each quantum is read in full before its bytes are
+// written, and they never land past it.
+pub enum DecodeSize {
+    Done(uint),       // on uncontinuable state
+    Next(uint),       // on continuable state
+    Fail(uint, ~str), // on decode failure
+}
+
+// Reads input byte `i` of a decoding core, from `dst` when decoding in
+// place.
+pub fn input_at(dst: &[u8], src: Option<&[u8]>, i: uint) -> u8 {
+    match src {
+        Some(src) => src[i],
+        None => dst[i]
+    }
+}
+
+// Turns encoded output into a string without a UTF-8 check, as every
+// encoding in this crate writes only ASCII.
+pub fn encoded_to_string(encoded: ~[u8]) -> ~str {
+    unsafe { str::raw::from_utf8_owned(encoded) }
+}
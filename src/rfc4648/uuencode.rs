@@ -0,0 +1,495 @@
+// uuencode.rs
+
+use std::cmp;
+use std::str;
+use std::rt::io::{Reader, Writer, Decorator};
+
+/// Line-oriented encodings of Unix mail and Usenet.
+///
+/// Every line starts with a character giving the number of bytes on it, at
+/// most 45, followed by groups of 3 bytes written as 4 characters. The data
+/// ends with a line of zero bytes. `Uuencode` writes a value `v` as the
+/// character `32 + v`, using `` ` `` for zero, and `Xxencode` uses the
+/// alphabet `+-0-9A-Za-z`. Files are framed by `begin <mode> <name>` and
+/// `end` lines.
+#[deriving(Eq, Clone)]
+pub enum UuType {
+    Uuencode,
+    Xxencode,
+}
+
+impl UuType {
+    /// Encodes `src` into body lines, without framing.
+    pub fn encode(self, src: &[u8]) -> ~[u8] {
+        let mut dst = ~[];
+        encode_body(self.table(), &mut dst, src);
+        dst
+    }
+    /// Decodes body lines, without framing, failing on bad input.
+    pub fn decode(self, src: &[u8]) -> ~[u8] {
+        match self.decode_result(src) {
+            Ok(dst) => dst,
+            Err(reason) => fail!(reason)
+        }
+    }
+    /// Decodes body lines, without framing, up to the line of zero bytes or
+    /// the end of `src`.
+    pub fn decode_result(self, src: &[u8]) -> Result<~[u8], ~str> {
+        let mut lines = SliceLines { src: src, pos: 0 };
+        let mut dst = ~[];
+        match decode_body(self, &mut lines, &mut dst) {
+            Ok(()) => Ok(dst),
+            Err(reason) => Err(reason)
+        }
+    }
+    /// Encodes `src` as a file named `name` with the permission bits `mode`.
+    pub fn encode_file(self, mode: uint, name: &str, src: &[u8]) -> ~[u8] {
+        let mut dst = begin_line(mode, name);
+        encode_body(self.table(), &mut dst, src);
+        dst.push_all(END_LINE);
+        dst
+    }
+    /// Decodes the first file in `src`, skipping any text before it.
+    pub fn decode_file(self, src: &[u8]) -> Result<UuFile, ~str> {
+        match self.files(src).next() {
+            Some(result) => result,
+            None => Err(~"missing begin line")
+        }
+    }
+    /// Iterates over the files of an archive, skipping any text between
+    /// them.
+    pub fn files<'a>(self, src: &'a [u8]) -> Files<'a> {
+        Files { uu_type: self, lines: SliceLines { src: src, pos: 0 }, done: false }
+    }
+    /// Like `files`, but reads the archive from `reader` a block at a time
+    /// as the files are decoded, instead of needing all of it in memory.
+    pub fn read_files<R: Reader>(self, reader: R) -> ReaderFiles<R> {
+        let lines = ReaderLines { reader: reader, buf: ~[], start: 0, pos: 0, eof: false };
+        ReaderFiles { uu_type: self, lines: lines, done: false }
+    }
+
+    fn table(self) -> &'static [u8] {
+        match self {
+            Uuencode => UUENCODE_TABLE,
+            Xxencode => XXENCODE_TABLE
+        }
+    }
+
+    fn decode_map(self) -> &'static [u8] {
+        match self {
+            Uuencode => UUENCODE_DECODE_MAP.as_slice(),
+            Xxencode => XXENCODE_DECODE_MAP.as_slice()
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Uuencode => "uuencode",
+            Xxencode => "xxencode"
+        }
+    }
+}
+
+/// A file of an archive.
+#[deriving(Eq, Clone)]
+pub struct UuFile {
+    mode: uint,
+    name: ~str,
+    data: ~[u8],
+}
+
+/// Iterator over the files of an archive, returned by `UuType::files`. It
+/// stops after the first error.
+pub struct Files<'a> {
+    priv uu_type: UuType,
+    priv lines: SliceLines<'a>,
+    priv done: bool,
+}
+
+impl<'a> Iterator<Result<UuFile, ~str>> for Files<'a> {
+    fn next(&mut self) -> Option<Result<UuFile, ~str>> {
+        next_file(self.uu_type, &mut self.lines, &mut self.done)
+    }
+}
+
+/// Iterator over the files of an archive read from a `Reader`, returned by
+/// `UuType::read_files`. It stops after the first error.
+pub struct ReaderFiles<R> {
+    priv uu_type: UuType,
+    priv lines: ReaderLines<R>,
+    priv done: bool,
+}
+
+impl<R: Reader> Iterator<Result<UuFile, ~str>> for ReaderFiles<R> {
+    fn next(&mut self) -> Option<Result<UuFile, ~str>> {
+        next_file(self.uu_type, &mut self.lines, &mut self.done)
+    }
+}
+
+impl<R: Reader> Decorator<R> for ReaderFiles<R> {
+    fn inner(self) -> R {
+        self.lines.reader
+    }
+
+    fn inner_ref<'a>(&'a self) -> &'a R {
+        &self.lines.reader
+    }
+
+    fn inner_mut_ref<'a>(&'a mut self) -> &'a mut R {
+        &mut self.lines.reader
+    }
+}
+
+// Where archives are decoded from: `pos` is the input position of the next
+// line, for error messages, and `next_line` returns that line without its
+// line ending.
+trait LineSource {
+    fn pos(&self) -> uint;
+    fn next_line<'a>(&'a mut self) -> Option<&'a [u8]>;
+}
+
+struct SliceLines<'a> {
+    src: &'a [u8],
+    pos: uint,
+}
+
+impl<'a> LineSource for SliceLines<'a> {
+    fn pos(&self) -> uint {
+        self.pos
+    }
+
+    fn next_line<'b>(&'b mut self) -> Option<&'b [u8]> {
+        next_line(self.src, &mut self.pos)
+    }
+}
+
+// Reads ahead in blocks. The line last returned stays in `buf` until the
+// next call, which drops it only when it needs to read more.
+struct ReaderLines<R> {
+    reader: R,
+    buf: ~[u8],
+    start: uint,
+    pos: uint,
+    eof: bool,
+}
+
+impl<R: Reader> LineSource for ReaderLines<R> {
+    fn pos(&self) -> uint {
+        self.pos
+    }
+
+    fn next_line<'a>(&'a mut self) -> Option<&'a [u8]> {
+        while !self.eof && self.buf.slice_from(self.start).position_elem(&NEWLINE).is_none() {
+            if self.start > 0 {
+                self.buf = self.buf.slice_from(self.start).to_owned();
+                self.start = 0;
+            }
+            let mut block = [0u8, ..READ_LEN];
+            match self.reader.read(block) {
+                Some(n) => self.buf.push_all(block.slice_to(n)),
+                None => self.eof = true
+            }
+        }
+
+        let start = self.start;
+        let mut next = start;
+        let line = next_line(self.buf, &mut next);
+        self.start = cmp::min(next, self.buf.len());
+        self.pos += self.start - start;
+        line
+    }
+}
+
+/// Writes files to an archive as their data arrives: each file is written
+/// between `begin` and `end`, with `Writer::write` in between.
+pub struct UuWriter<W> {
+    priv uu_type: UuType,
+    priv writer: W,
+    priv buf: [u8, ..LINE_LEN],
+    priv len: uint,
+    priv open: bool,
+}
+
+impl<W: Writer> UuWriter<W> {
+    /// Wraps `writer`; each file goes between `begin` and `end` calls.
+    pub fn new(uu_type: UuType, writer: W) -> UuWriter<W> {
+        UuWriter { uu_type: uu_type, writer: writer, buf: [0u8, ..LINE_LEN], len: 0, open: false }
+    }
+
+    /// Starts a file by writing its `begin` line.
+    pub fn begin(&mut self, mode: uint, name: &str) {
+        assert!(!self.open);
+        self.writer.write(begin_line(mode, name));
+        self.open = true;
+    }
+
+    /// Finishes the current file with its last lines and `end` line.
+    pub fn end(&mut self) {
+        assert!(self.open);
+        let mut dst = ~[];
+        let table = self.uu_type.table();
+        if self.len > 0 {
+            encode_line(table, &mut dst, self.buf.slice_to(self.len));
+        }
+        encode_line(table, &mut dst, []);
+        dst.push_all(END_LINE);
+        self.writer.write(dst);
+        self.len = 0;
+        self.open = false;
+    }
+}
+
+impl<W: Writer> Writer for UuWriter<W> {
+    fn write(&mut self, buf: &[u8]) {
+        assert!(self.open);
+        let table = self.uu_type.table();
+        let mut dst = ~[];
+        for &b in buf.iter() {
+            self.buf[self.len] = b;
+            self.len += 1;
+            if self.len == LINE_LEN {
+                encode_line(table, &mut dst, self.buf);
+                self.len = 0;
+            }
+        }
+        if dst.len() > 0 {
+            self.writer.write(dst);
+        }
+    }
+
+    fn flush(&mut self) {
+        self.writer.flush()
+    }
+}
+
+impl<W: Writer> Decorator<W> for UuWriter<W> {
+    fn inner(self) -> W {
+        self.writer
+    }
+
+    fn inner_ref<'a>(&'a self) -> &'a W {
+        &self.writer
+    }
+
+    fn inner_mut_ref<'a>(&'a mut self) -> &'a mut W {
+        &mut self.writer
+    }
+}
+
+static LINE_LEN: uint = 45;
+static READ_LEN: uint = 4096;
+static NEWLINE: u8 = 10; // '\n'
+static BEGIN: &'static [u8] = bytes!("begin ");
+static END: &'static [u8] = bytes!("end");
+static END_LINE: &'static [u8] = bytes!("end\n");
+
+static UUENCODE_TABLE: &'static [u8] =
+    bytes!("`!\"#$%&'()*+,-./0123456789:;<=>?",
+           "@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_");
+
+static XXENCODE_TABLE: &'static [u8] =
+    bytes!("+-0123456789",
+           "ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+           "abcdefghijklmnopqrstuvwxyz");
+
+// Both a space and a backquote decode to zero.
+static UUENCODE_DECODE_MAP: [u8, ..256] = [
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+      0,   1,   2,   3,   4,   5,   6,   7,   8,   9,  10,  11,  12,  13,  14,  15,
+     16,  17,  18,  19,  20,  21,  22,  23,  24,  25,  26,  27,  28,  29,  30,  31,
+     32,  33,  34,  35,  36,  37,  38,  39,  40,  41,  42,  43,  44,  45,  46,  47,
+     48,  49,  50,  51,  52,  53,  54,  55,  56,  57,  58,  59,  60,  61,  62,  63,
+      0, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+];
+
+static XXENCODE_DECODE_MAP: [u8, ..256] = [
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,   0, 255,   1, 255, 255,
+      2,   3,   4,   5,   6,   7,   8,   9,  10,  11, 255, 255, 255, 255, 255, 255,
+    255,  12,  13,  14,  15,  16,  17,  18,  19,  20,  21,  22,  23,  24,  25,  26,
+     27,  28,  29,  30,  31,  32,  33,  34,  35,  36,  37, 255, 255, 255, 255, 255,
+    255,  38,  39,  40,  41,  42,  43,  44,  45,  46,  47,  48,  49,  50,  51,  52,
+     53,  54,  55,  56,  57,  58,  59,  60,  61,  62,  63, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+];
+
+fn begin_line(mode: uint, name: &str) -> ~[u8] {
+    format!("begin {:o} {}\n", mode, name).into_bytes()
+}
+
+fn encode_body(table: &[u8], dst: &mut ~[u8], src: &[u8]) {
+    for line in src.chunk_iter(LINE_LEN) {
+        encode_line(table, dst, line);
+    }
+    encode_line(table, dst, []);
+}
+
+fn encode_line(table: &[u8], dst: &mut ~[u8], src: &[u8]) {
+    dst.push(table[src.len()]);
+    for group in src.chunk_iter(3) {
+        let mut n = 0u32;
+        for k in range(0u, 3) {
+            n = n << 8 | if k < group.len() { group[k] as u32 } else { 0 };
+        }
+        dst.push(table[n>>18 & 0x3f]);
+        dst.push(table[n>>12 & 0x3f]);
+        dst.push(table[n>>6  & 0x3f]);
+        dst.push(table[n     & 0x3f]);
+    }
+    dst.push(NEWLINE);
+}
+
+// Returns the line at `*pos` without its line ending and moves `*pos` to
+// the start of the next line.
+fn next_line<'a>(src: &'a [u8], pos: &mut uint) -> Option<&'a [u8]> {
+    if *pos >= src.len() {
+        return None;
+    }
+    let start = *pos;
+    let end = match src.slice_from(start).position_elem(&NEWLINE) {
+        Some(k) => start + k,
+        None => src.len()
+    };
+    *pos = end + 1;
+
+    let line = src.slice(start, end);
+    if line.len() > 0 && line[line.len() - 1] == 13 {
+        Some(line.slice_to(line.len() - 1))
+    } else {
+        Some(line)
+    }
+}
+
+// Skips to the next `begin` line and decodes the file after it. Sets
+// `*done` at the end of the input and after an error.
+fn next_file<L: LineSource>(uu_type: UuType, lines: &mut L, done: &mut bool) -> Option<Result<UuFile, ~str>> {
+    if *done {
+        return None;
+    }
+
+    let mut header = None;
+    while header.is_none() {
+        match lines.next_line() {
+            Some(line) if line.starts_with(BEGIN) => header = Some(parse_begin(line)),
+            Some(_) => (),
+            None => {
+                *done = true;
+                return None;
+            }
+        }
+    }
+
+    let result = match header.unwrap() {
+        Ok((mode, name)) => decode_file(uu_type, mode, name, lines),
+        Err(reason) => Err(reason)
+    };
+    *done = result.is_err();
+    Some(result)
+}
+
+// Parses the mode and file name of a `begin` line.
+fn parse_begin(header: &[u8]) -> Result<(uint, ~str), ~str> {
+    let rest = header.slice_from(BEGIN.len());
+    let mut mode = 0u;
+    let mut i = 0;
+    while i < rest.len() && rest[i] >= 48 && rest[i] <= 55 {
+        mode = mode * 8 + (rest[i] - 48) as uint;
+        i += 1;
+    }
+    if i == 0 || i + 1 >= rest.len() || rest[i] != 32 {
+        return Err(~"malformed begin line");
+    }
+    let name = rest.slice_from(i + 1);
+    if !str::is_utf8(name) {
+        return Err(~"file name in begin line is not utf-8");
+    }
+    Ok((mode, str::from_utf8(name)))
+}
+
+// Decodes the body and `end` line of the file whose `begin` line was just
+// read.
+fn decode_file<L: LineSource>(uu_type: UuType, mode: uint, name: ~str, lines: &mut L) -> Result<UuFile, ~str> {
+    let mut data = ~[];
+    match decode_body(uu_type, lines, &mut data) {
+        Ok(()) => (),
+        Err(reason) => return Err(reason)
+    }
+    let ended = match lines.next_line() {
+        Some(line) => line == END,
+        None => false
+    };
+    if !ended {
+        return Err(format!("missing end line for {}", name));
+    }
+    Ok(UuFile { mode: mode, name: name, data: data })
+}
+
+// Decodes lines up to and including the line of zero bytes, or up to the
+// end of the input.
+fn decode_body<L: LineSource>(uu_type: UuType, lines: &mut L, dst: &mut ~[u8]) -> Result<(), ~str> {
+    let decode_map = uu_type.decode_map();
+
+    loop {
+        let start = lines.pos();
+        let line = match lines.next_line() {
+            Some(line) => line,
+            None => return Ok(())
+        };
+        if line.len() == 0 {
+            return Err(format!("empty {} line at input byte {}", uu_type.name(), start));
+        }
+
+        let n = decode_map[line[0]];
+        if n == 0xff {
+            return Err(fail_decode_at(uu_type, start));
+        }
+        if n == 0 {
+            return Ok(());
+        }
+
+        let n = n as uint;
+        let need = (n + 2) / 3 * 4;
+        if line.len() < 1 + need {
+            return Err(format!("{} line too short at input byte {}", uu_type.name(), start));
+        }
+
+        let mut remain = n;
+        for g in range(0, need / 4) {
+            let mut v = 0u32;
+            for k in range(0u, 4) {
+                let c = decode_map[line[1 + g*4 + k]];
+                if c == 0xff {
+                    return Err(fail_decode_at(uu_type, start + 1 + g*4 + k));
+                }
+                v = v << 6 | c as u32;
+            }
+            let m = cmp::min(remain, 3);
+            for k in range(0u, m) {
+                dst.push((v >> (16 - 8 * k)) as u8);
+            }
+            remain -= m;
+        }
+    }
+}
+
+fn fail_decode_at(uu_type: UuType, n: uint) -> ~str {
+    format!("illegal {} data at input byte {}", uu_type.name(), n)
+}
@@ -0,0 +1,144 @@
+// validated.rs
+
+use base16::Base16Type;
+use base32;
+use base64;
+use codec::Codec;
+
+/// Owned encoded text that has been validated by its codec, so that it can
+/// be passed around without revalidation and decoded without failure.
+pub struct EncodedString<C> {
+    priv codec: C,
+    priv text: ~str,
+    priv decoded_len: uint,
+}
+
+/// Borrowed counterpart of `EncodedString`.
+pub struct EncodedStr<'a, C> {
+    priv codec: C,
+    priv text: &'a str,
+    priv decoded_len: uint,
+}
+
+// A unit type standing for one variant of a codec, so that the variant of
+// an `EncodedString` is part of its type rather than a runtime value.
+macro_rules! variant_codec(
+    ($name:ident, $codec:expr) => (
+        #[deriving(Eq, Clone)]
+        pub struct $name;
+
+        impl Codec for $name {
+            fn encoded_len(&self, n: uint) -> uint {
+                $codec.encoded_len(n)
+            }
+            fn decoded_len(&self, n: uint) -> uint {
+                $codec.decoded_len(n)
+            }
+            fn block_len(&self) -> (uint, uint) {
+                $codec.block_len()
+            }
+            fn encode_to(&self, dst: &mut [u8], src: &[u8]) {
+                $codec.encode_to(dst, src)
+            }
+            fn decode_partial_to(&self, dst: &mut [u8], src: &[u8]) -> Result<uint, (uint, ~str)> {
+                $codec.decode_partial_to(dst, src)
+            }
+            fn validate(&self, src: &[u8]) -> Result<uint, ~str> {
+                $codec.validate(src)
+            }
+            fn decode_in_place(&self, buf: &mut [u8]) -> Result<uint, (uint, ~str)> {
+                $codec.decode_in_place(buf)
+            }
+        }
+    )
+)
+
+variant_codec!(Base32Standard, base32::Standard)
+variant_codec!(Base64Standard, base64::Standard)
+variant_codec!(Base64UrlSafe, base64::UrlSafe)
+
+pub type Base16String = EncodedString<Base16Type>;
+pub type Base32String = EncodedString<Base32Standard>;
+pub type Base64String = EncodedString<Base64Standard>;
+pub type Base64UrlString = EncodedString<Base64UrlSafe>;
+
+pub type Base16Str<'a> = EncodedStr<'a, Base16Type>;
+pub type Base32Str<'a> = EncodedStr<'a, Base32Standard>;
+pub type Base64Str<'a> = EncodedStr<'a, Base64Standard>;
+pub type Base64UrlStr<'a> = EncodedStr<'a, Base64UrlSafe>;
+
+impl<C: Codec + Clone> EncodedString<C> {
+    /// Validates `text` with `codec`, e.g. `EncodedString::new(base64::UrlSafe, text)`.
+    pub fn new(codec: C, text: ~str) -> Result<EncodedString<C>, ~str> {
+        let result = codec.validate(text.as_bytes());
+        match result {
+            Ok(n) => Ok(EncodedString { codec: codec, text: text, decoded_len: n }),
+            Err(reason) => Err(reason)
+        }
+    }
+
+    /// Returns the codec the text was validated with.
+    pub fn codec(&self) -> C {
+        self.codec.clone()
+    }
+
+    /// Returns the encoded text.
+    pub fn as_str<'a>(&'a self) -> &'a str {
+        self.text.as_slice()
+    }
+
+    /// Borrows the text as an `EncodedStr`, keeping its validation.
+    pub fn as_encoded_str<'a>(&'a self) -> EncodedStr<'a, C> {
+        EncodedStr { codec: self.codec.clone(), text: self.text.as_slice(), decoded_len: self.decoded_len }
+    }
+
+    /// Returns the encoded text, giving up its validation.
+    pub fn into_str(self) -> ~str {
+        self.text
+    }
+
+    /// Returns the length of the decoded data without decoding it.
+    pub fn decoded_len(&self) -> uint {
+        self.decoded_len
+    }
+
+    /// Decodes the text, which cannot fail since it has been validated.
+    pub fn decode(&self) -> ~[u8] {
+        self.codec.decode(self.text.as_bytes())
+    }
+}
+
+impl<'a, C: Codec + Clone> EncodedStr<'a, C> {
+    /// Validates `text` with `codec`, e.g. `EncodedStr::new(base16::Standard, text)`.
+    pub fn new(codec: C, text: &'a str) -> Result<EncodedStr<'a, C>, ~str> {
+        match codec.validate(text.as_bytes()) {
+            Ok(n) => Ok(EncodedStr { codec: codec, text: text, decoded_len: n }),
+            Err(reason) => Err(reason)
+        }
+    }
+
+    /// Returns the codec the text was validated with.
+    pub fn codec(&self) -> C {
+        self.codec.clone()
+    }
+
+    /// Returns the encoded text.
+    pub fn as_str(&self) -> &'a str {
+        self.text
+    }
+
+    /// Copies the text into an `EncodedString`, keeping its validation.
+    pub fn to_encoded_string(&self) -> EncodedString<C> {
+        EncodedString { codec: self.codec.clone(), text: self.text.to_owned(), decoded_len: self.decoded_len }
+    }
+
+    /// Returns the length of the decoded data without decoding it.
+    pub fn decoded_len(&self) -> uint {
+        self.decoded_len
+    }
+
+    /// Decodes the text, which cannot fail since it has been validated.
+    pub fn decode(&self) -> ~[u8] {
+        self.codec.decode(self.text.as_bytes())
+    }
+}
@@ -0,0 +1,49 @@
+extern mod rfc4648;
+
+use std::os;
+use std::str;
+use std::rt::io;
+use std::rt::io::{Reader, Writer};
+use std::rt::io::File;
+
+use rfc4648::encoding::Encoding;
+
+fn main() {
+    let args = os::args();
+
+    let binary = &args[0];
+    let mut stderr = io::stderr();
+    let mut stdout = io::stdout();
+
+    if args.len() < 4 {
+        let usage = format!("Usage: {} <encoding> <name> <filename>\n", *binary);
+        stderr.write(usage.into_bytes());
+        stderr.flush();
+        os::set_exit_status(1);
+        return;
+    }
+
+    let encoding = match Encoding::from_name(args[1].as_slice()) {
+        Some(encoding) => encoding,
+        None => {
+            stderr.write(format!("unknown encoding {}\n", args[1]).into_bytes());
+            os::set_exit_status(1);
+            return;
+        }
+    };
+
+    // line breaks and indentation around the encoded text are dropped
+    let path = Path::new(args[3].clone());
+    let data = File::open(&path).read_to_end();
+    let text: ~[u8] = data.move_iter().filter(|&b| {
+        b != ' ' as u8 && b != '\t' as u8 && b != '\r' as u8 && b != '\n' as u8
+    }).collect();
+
+    match encoding.static_source(args[2].as_slice(), str::from_utf8(text)) {
+        Ok(source) => stdout.write(source.into_bytes()),
+        Err(reason) => {
+            stderr.write(format!("{}: {}\n", args[3], reason).into_bytes());
+            os::set_exit_status(1);
+        }
+    }
+}